@@ -0,0 +1,94 @@
+use crate::error::Error;
+use crate::version::{Requirement, Version};
+use serde::Deserialize;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// A security advisory from a ruby-advisory-db checkout, as stored in
+/// `gems/<gem>/<id>.yml`.
+#[derive(Deserialize)]
+pub struct Advisory {
+    pub title: String,
+    pub url: Option<String>,
+    cve: Option<String>,
+    ghsa: Option<String>,
+    #[serde(default)]
+    patched_versions: Vec<String>,
+    #[serde(default)]
+    unaffected_versions: Vec<String>,
+    /// The file name without its extension, for advisories with neither a
+    /// CVE nor a GHSA identifier.
+    #[serde(skip)]
+    name: String,
+    #[serde(skip)]
+    patched: Vec<Requirement>,
+    #[serde(skip)]
+    unaffected: Vec<Requirement>,
+}
+
+impl Advisory {
+    /// The advisory's CVE identifier, falling back to its GHSA one.
+    pub fn id(&self) -> String {
+        match (&self.cve, &self.ghsa) {
+            (Some(cve), _) => format!("CVE-{}", cve),
+            (None, Some(ghsa)) => format!("GHSA-{}", ghsa),
+            (None, None) => self.name.clone(),
+        }
+    }
+
+    /// Whether `version` is neither patched nor unaffected.
+    pub fn affects(&self, version: &Version) -> bool {
+        !self
+            .patched
+            .iter()
+            .chain(&self.unaffected)
+            .any(|requirement| requirement.satisfied_by(version))
+    }
+
+    /// The requirements a version must meet to be patched, e.g. `>= 2.2.8.1`.
+    pub fn patched_versions(&self) -> &[String] {
+        &self.patched_versions
+    }
+}
+
+/// Reads every advisory filed against `gem`, ordered by file name.
+pub fn for_gem(db: &Path, gem: &str) -> Result<Vec<Advisory>, Error> {
+    let dir = db.join("gems").join(gem);
+    let files = match fs::read_dir(&dir) {
+        Ok(files) => files,
+        Err(e) if e.kind() == io::ErrorKind::NotFound && db.join("gems").is_dir() => {
+            return Ok(Vec::new())
+        }
+        Err(e) => return Err(Error::AdvisoryDbUnreadable(dir, e)),
+    };
+    let mut paths = files
+        .map(|file| file.map(|file| file.path()))
+        .collect::<Result<Vec<_>, io::Error>>()
+        .map_err(|e| Error::AdvisoryDbUnreadable(dir.clone(), e))?;
+    paths.retain(|path| path.extension().is_some_and(|extension| extension == "yml"));
+    paths.sort();
+
+    paths
+        .into_iter()
+        .map(|path| {
+            let contents = fs::read_to_string(&path)
+                .map_err(|e| Error::AdvisoryDbUnreadable(path.clone(), e))?;
+            let mut advisory: Advisory = serde_yaml::from_str(&contents)
+                .map_err(|e| Error::InvalidAdvisory(path.clone(), e.to_string()))?;
+            let requirements = |list: &[String]| {
+                list.iter()
+                    .map(|requirement| requirement.parse::<Requirement>())
+                    .collect::<Result<Vec<_>, String>>()
+                    .map_err(|e| Error::InvalidAdvisory(path.clone(), e))
+            };
+            advisory.patched = requirements(&advisory.patched_versions)?;
+            advisory.unaffected = requirements(&advisory.unaffected_versions)?;
+            advisory.name = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            Ok(advisory)
+        })
+        .collect()
+}
@@ -0,0 +1,168 @@
+use chrono::Utc;
+use git2::{Blame, DiffOptions, Oid, Patch, Repository, Signature};
+use std::collections::{HashMap, HashSet};
+
+/// The name and email from a commit signature.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Person {
+    pub name: String,
+    pub email: String,
+}
+
+impl Person {
+    pub fn from_signature(signature: &Signature) -> Person {
+        Person {
+            name: signature.name().unwrap_or("unknown").to_string(),
+            email: signature.email().unwrap_or("").to_string(),
+        }
+    }
+
+    /// Who git blame credits with lines that differ from HEAD.
+    fn not_committed_yet() -> Person {
+        Person {
+            name: "Not Committed Yet".to_string(),
+            email: "not.committed.yet".to_string(),
+        }
+    }
+}
+
+/// A run of consecutive lockfile lines last changed by the same commit.
+#[derive(Clone)]
+pub struct Hunk {
+    pub commit: Oid,
+    pub author: Person,
+    pub committer: Person,
+    pub seconds: i64,
+}
+
+impl Hunk {
+    /// Whether the lines are committed, rather than only changed in the
+    /// working tree.
+    pub fn is_committed(&self) -> bool {
+        !self.commit.is_zero()
+    }
+}
+
+/// The blame of a lockfile, answering which hunk owns each line.
+pub struct Attribution {
+    hunks: Vec<Hunk>,
+    /// Index into `hunks` for every zero-based line number.
+    lines: Vec<Option<usize>>,
+}
+
+impl Attribution {
+    /// Builds the attribution from a blame, resolving authors and committers
+    /// through the repository's `.mailmap`.
+    pub fn from_blame(repo: &Repository, blame: &Blame) -> Result<Attribution, git2::Error> {
+        let mailmap = repo.mailmap()?;
+        let mut hunks = Vec::new();
+        let mut lines = Vec::new();
+        // Many hunks share a commit, so each one is only looked up once.
+        let mut people: HashMap<Oid, (Person, Person)> = HashMap::new();
+
+        for hunk in blame.iter() {
+            let signature = hunk.final_signature();
+            // Blame lines are one-based, lockfile lines are zero-based.
+            let start = hunk.final_start_line() - 1;
+            let len = hunk.lines_in_hunk();
+
+            if lines.len() < start + len {
+                lines.resize(start + len, None);
+            }
+            for owner in &mut lines[start..start + len] {
+                *owner = Some(hunks.len());
+            }
+
+            let commit = hunk.final_commit_id();
+            let (author, committer) = match people.get(&commit) {
+                Some(people) => people.clone(),
+                None => {
+                    let found = repo.find_commit(commit)?;
+                    let author = Person::from_signature(&found.author_with_mailmap(&mailmap)?);
+                    let committer =
+                        Person::from_signature(&found.committer_with_mailmap(&mailmap)?);
+                    people.insert(commit, (author.clone(), committer.clone()));
+                    (author, committer)
+                }
+            };
+
+            hunks.push(Hunk {
+                commit,
+                author,
+                committer,
+                seconds: signature.when().seconds(),
+            });
+        }
+
+        Ok(Attribution { hunks, lines })
+    }
+
+    /// Carries the blame of `committed` over to `contents`, its version in the
+    /// working tree, the way git blame does for a modified file.
+    ///
+    /// Lines the diff between them keeps stay with their hunk, and added or
+    /// changed lines go to "Not Committed Yet" as of now, under a zero commit.
+    pub fn onto_buffer(self, committed: &str, contents: &str) -> Result<Attribution, git2::Error> {
+        let mut options = DiffOptions::new();
+        options.context_lines(0);
+        let patch = Patch::from_buffers(
+            committed.as_bytes(),
+            None,
+            contents.as_bytes(),
+            None,
+            Some(&mut options),
+        )?;
+        let mut removed = HashSet::new();
+        let mut added = HashSet::new();
+        for hunk in 0..patch.num_hunks() {
+            for line in 0..patch.num_lines_in_hunk(hunk)? {
+                let line = patch.line_in_hunk(hunk, line)?;
+                // Diff lines are one-based, lockfile lines are zero-based.
+                match (line.old_lineno(), line.new_lineno()) {
+                    (Some(old), None) => removed.insert(old as usize - 1),
+                    (None, Some(new)) => added.insert(new as usize - 1),
+                    _ => false,
+                };
+            }
+        }
+
+        let Attribution { mut hunks, lines } = self;
+        let uncommitted = hunks.len();
+        hunks.push(Hunk {
+            commit: Oid::zero(),
+            author: Person::not_committed_yet(),
+            committer: Person::not_committed_yet(),
+            seconds: Utc::now().timestamp(),
+        });
+        let mut old = 0;
+        let lines = (0..contents.lines().count())
+            .map(|new| {
+                if added.contains(&new) {
+                    return Some(uncommitted);
+                }
+                while removed.contains(&old) {
+                    old += 1;
+                }
+                old += 1;
+                lines.get(old - 1).copied().flatten()
+            })
+            .collect();
+
+        Ok(Attribution { hunks, lines })
+    }
+
+    /// Attributes the zero-based `line` to `hunk` instead of its blamed hunk.
+    pub fn reassign(&mut self, line: usize, hunk: Hunk) {
+        if self.lines.len() <= line {
+            self.lines.resize(line + 1, None);
+        }
+        self.lines[line] = Some(self.hunks.len());
+        self.hunks.push(hunk);
+    }
+
+    /// The hunk that last changed the zero-based `line`.
+    pub fn line(&self, line: usize) -> Option<&Hunk> {
+        let index = (*self.lines.get(line)?)?;
+        Some(&self.hunks[index])
+    }
+}
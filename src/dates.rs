@@ -0,0 +1,119 @@
+use crate::error::Error;
+use crate::staleness::SECONDS_PER_DAY;
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local, TimeZone, Utc};
+use chrono_tz::Tz;
+use clap::ValueEnum;
+use std::str::FromStr;
+
+/// The time zone dates are shown in.
+#[derive(Clone, Copy, Debug)]
+pub enum Zone {
+    Utc,
+    Local,
+    Named(Tz),
+}
+
+impl FromStr for Zone {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Zone, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "utc" => Ok(Zone::Utc),
+            "local" => Ok(Zone::Local),
+            _ => s.parse::<Tz>().map(Zone::Named).map_err(|_| {
+                format!(
+                    "unknown time zone `{}`, expected utc, local or an IANA name like Europe/Berlin",
+                    s
+                )
+            }),
+        }
+    }
+}
+
+/// How finely the date report groups gems.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum Granularity {
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+/// Turns commit timestamps into the dates shown in reports.
+pub struct Dates {
+    pub zone: Zone,
+    /// A strftime format for single dates, `%Y-%m-%d` by default.
+    pub format: String,
+    /// Whether to add how long ago each date was.
+    pub relative: bool,
+    pub now: i64,
+}
+
+impl Dates {
+    /// Formats a single date, e.g. `2023-06-05` or `2023-06-05 (3 months ago)`.
+    pub fn format(&self, seconds: i64) -> Result<String, Error> {
+        let date = self.in_zone(seconds, &self.format)?;
+        Ok(self.with_relative(date, seconds))
+    }
+
+    /// The label of the period containing `seconds`, used to group the date
+    /// report. Day groups use the date format; coarser groups use ISO forms
+    /// such as `2023-W23`, `2023-06` and `2023`.
+    ///
+    /// Changes with the same label share a group, so a date format finer or
+    /// coarser than a day still yields one group per label.
+    pub fn period(&self, seconds: i64, granularity: Granularity) -> Result<String, Error> {
+        let format = match granularity {
+            Granularity::Day => self.format.as_str(),
+            Granularity::Week => "%G-W%V",
+            Granularity::Month => "%Y-%m",
+            Granularity::Year => "%Y",
+        };
+        self.in_zone(seconds, format)
+    }
+
+    fn in_zone(&self, seconds: i64, format: &str) -> Result<String, Error> {
+        let formatted = match self.zone {
+            Zone::Utc => to_zone(&Utc, seconds).map(|date| date.format(format).to_string()),
+            Zone::Local => to_zone(&Local, seconds).map(|date| date.format(format).to_string()),
+            Zone::Named(tz) => to_zone(&tz, seconds).map(|date| date.format(format).to_string()),
+        };
+        formatted.ok_or(Error::InvalidTimestamp(seconds))
+    }
+
+    /// Adds how long ago `seconds` was to `label`, if relative dates are on.
+    pub fn with_relative(&self, label: String, seconds: i64) -> String {
+        if self.relative {
+            format!("{} ({})", label, ago(self.now - seconds))
+        } else {
+            label
+        }
+    }
+}
+
+/// Checks a `--date-format` argument, since chrono panics when asked to
+/// render an invalid strftime format.
+pub fn parse_format(format: &str) -> Result<String, String> {
+    if StrftimeItems::new(format).any(|item| item == Item::Error) {
+        return Err(format!("`{}` is not a valid strftime format", format));
+    }
+    Ok(format.to_string())
+}
+
+fn to_zone<Z: TimeZone>(zone: &Z, seconds: i64) -> Option<DateTime<Z>> {
+    zone.timestamp_opt(seconds, 0).single()
+}
+
+/// Describes a duration in the past, e.g. `3 months ago`.
+pub fn ago(seconds: i64) -> String {
+    let days = seconds / SECONDS_PER_DAY;
+    let (amount, unit) = match days {
+        i64::MIN..=0 => return "today".to_string(),
+        1..=29 => (days, "day"),
+        30..=364 => (days / 30, "month"),
+        _ => (days / 365, "year"),
+    };
+    let plural = if amount == 1 { "" } else { "s" };
+    format!("{} {}{} ago", amount, unit, plural)
+}
@@ -0,0 +1,48 @@
+use crate::lockfile;
+use crate::version::{self, Level};
+use std::cmp::Ordering;
+
+/// How a single gem differs between two lockfiles.
+pub enum Change {
+    Added(String),
+    Removed(String),
+    Upgraded(String, String, Level),
+    Downgraded(String, String, Level),
+}
+
+/// Compares the resolved gems of two lockfiles, returning one change per gem
+/// and platform that was added, removed or moved to another version, ordered
+/// by name.
+///
+/// Platform variants are compared on their own, so adding a variant for a
+/// new platform is reported as added rather than as an upgrade.
+pub fn diff(old: &str, new: &str) -> Vec<(String, Option<String>, Change)> {
+    let old = lockfile::versions(&lockfile::parse(old));
+    let mut new = lockfile::versions(&lockfile::parse(new));
+    let mut changes = Vec::new();
+
+    for ((name, platform), from) in old {
+        let change = match new.remove(&(name.clone(), platform.clone())) {
+            None => Change::Removed(from),
+            Some(to) => {
+                let level = match version::level(&from, &to) {
+                    Some(level) => level,
+                    None => continue,
+                };
+                match version::compare(&from, &to) {
+                    Ordering::Less => Change::Upgraded(from, to, level),
+                    Ordering::Greater => Change::Downgraded(from, to, level),
+                    Ordering::Equal => continue,
+                }
+            }
+        };
+        changes.push((name, platform, change));
+    }
+    changes.extend(
+        new.into_iter()
+            .map(|((name, platform), version)| (name, platform, Change::Added(version))),
+    );
+    changes.sort_by(|(a, a_platform, _), (b, b_platform, _)| (a, a_platform).cmp(&(b, b_platform)));
+
+    changes
+}
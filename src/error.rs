@@ -0,0 +1,129 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// Exit code when a gem is older than `--warn-age`.
+pub const EXIT_WARN_AGE: u8 = 3;
+/// Exit code when a gem is older than `--max-age`.
+pub const EXIT_MAX_AGE: u8 = 4;
+/// Exit code when `depr audit` finds a vulnerable gem.
+pub const EXIT_VULNERABLE: u8 = 5;
+
+/// Everything that can stop depr from producing a report.
+///
+/// Each variant maps to its own process exit code, starting at 10. Codes 1
+/// and 2 are left to unexpected git failures and clap's usage errors, and 3
+/// to 5 to reports that ran but found stale or vulnerable gems.
+#[derive(Debug)]
+pub enum Error {
+    /// There is no lockfile at the given path.
+    LockfileNotFound(PathBuf),
+    /// The lockfile exists but could not be read.
+    LockfileUnreadable(PathBuf, io::Error),
+    /// The directory is not inside a git repository.
+    NotARepository(PathBuf, git2::Error),
+    /// The lockfile is not committed, so there is nothing to blame.
+    Untracked(PathBuf),
+    /// A commit carries a timestamp chrono cannot represent.
+    InvalidTimestamp(i64),
+    /// A `--rev` argument does not name a commit.
+    UnknownRevision(String, git2::Error),
+    /// The lockfile does not exist at the requested revision.
+    NotAtRevision(PathBuf, String),
+    /// A file of the local gem index could not be read.
+    IndexUnreadable(PathBuf, io::Error),
+    /// A file of the local gem index is not in the expected format.
+    InvalidIndex(PathBuf, String),
+    /// A file of the advisory database could not be read.
+    AdvisoryDbUnreadable(PathBuf, io::Error),
+    /// An advisory is not in the expected format.
+    InvalidAdvisory(PathBuf, String),
+    /// The report could not be serialized.
+    Output(serde_json::Error),
+    /// Any other failure reported by libgit2.
+    Git(git2::Error),
+}
+
+impl Error {
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Error::Git(_) => 1,
+            Error::LockfileNotFound(_) => 10,
+            Error::LockfileUnreadable(..) => 11,
+            Error::NotARepository(..) => 12,
+            Error::Untracked(_) => 13,
+            Error::InvalidTimestamp(_) => 14,
+            Error::Output(_) => 15,
+            Error::UnknownRevision(..) => 16,
+            Error::NotAtRevision(..) => 17,
+            Error::IndexUnreadable(..) => 18,
+            Error::InvalidIndex(..) => 19,
+            Error::AdvisoryDbUnreadable(..) => 20,
+            Error::InvalidAdvisory(..) => 21,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::LockfileNotFound(path) => write!(f, "no lockfile found at {}", path.display()),
+            Error::LockfileUnreadable(path, e) => {
+                write!(f, "could not read {}: {}", path.display(), e)
+            }
+            Error::NotARepository(path, e) => write!(
+                f,
+                "{} is not a git repository: {}",
+                path.display(),
+                e.message()
+            ),
+            Error::Untracked(path) => write!(
+                f,
+                "{} is not tracked by git, commit it before running depr",
+                path.display()
+            ),
+            Error::InvalidTimestamp(seconds) => {
+                write!(f, "commit timestamp {} is out of range", seconds)
+            }
+            Error::UnknownRevision(rev, e) => {
+                write!(f, "unknown revision `{}`: {}", rev, e.message())
+            }
+            Error::NotAtRevision(path, rev) => {
+                write!(f, "{} does not exist at {}", path.display(), rev)
+            }
+            Error::IndexUnreadable(path, e) => {
+                write!(f, "could not read {}: {}", path.display(), e)
+            }
+            Error::InvalidIndex(path, reason) => {
+                write!(
+                    f,
+                    "{} is not a valid index file: {}",
+                    path.display(),
+                    reason
+                )
+            }
+            Error::AdvisoryDbUnreadable(path, e) => {
+                write!(f, "could not read {}: {}", path.display(), e)
+            }
+            Error::InvalidAdvisory(path, reason) => {
+                write!(f, "{} is not a valid advisory: {}", path.display(), reason)
+            }
+            Error::Output(e) => write!(f, "could not write the report: {}", e),
+            Error::Git(e) => write!(f, "git error: {}", e.message()),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<git2::Error> for Error {
+    fn from(e: git2::Error) -> Error {
+        Error::Git(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Error {
+        Error::Output(e)
+    }
+}
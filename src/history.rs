@@ -0,0 +1,288 @@
+use crate::blame::{Attribution, Hunk, Person};
+use crate::lockfile::{self, Entry, Kind};
+use git2::{Commit, Oid, Repository, Sort};
+use std::collections::{hash_map, BTreeMap, HashMap, HashSet};
+use std::path::Path;
+
+/// A commit that changed the lockfile, along with the lockfile at that commit.
+pub struct Revision {
+    pub commit: Oid,
+    pub author: Person,
+    pub committer: Person,
+    pub seconds: i64,
+    /// The lockfile contents, or `None` if the commit deleted it.
+    pub contents: Option<String>,
+    /// The lockfile in each parent, or `None` where a parent lacks it.
+    pub parents: Vec<Option<String>>,
+}
+
+/// A change in the version of a gem pinned by the lockfile.
+pub struct Transition {
+    pub commit: Oid,
+    pub author: Person,
+    pub seconds: i64,
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+/// Walks the history of `start`, or HEAD, oldest first, and returns every
+/// commit where the blob at `path` differs from the blob in each of its
+/// parents.
+///
+/// Like git's default history simplification, a merge that takes the
+/// lockfile from one of its parents is skipped, so changes are credited to
+/// the commit on the branch that made them rather than to the merge.
+pub fn revisions(
+    repo: &Repository,
+    path: &Path,
+    start: Option<Oid>,
+) -> Result<Vec<Revision>, git2::Error> {
+    let mut revwalk = repo.revwalk()?;
+    match start {
+        Some(start) => revwalk.push(start)?,
+        None => revwalk.push_head()?,
+    }
+    revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME | Sort::REVERSE)?;
+
+    let mailmap = repo.mailmap()?;
+    let mut blobs: HashMap<Oid, String> = HashMap::new();
+    let mut read = |id: Option<Oid>| -> Result<Option<String>, git2::Error> {
+        let id = match id {
+            Some(id) => id,
+            None => return Ok(None),
+        };
+        if let hash_map::Entry::Vacant(entry) = blobs.entry(id) {
+            let blob = repo.find_blob(id)?;
+            entry.insert(String::from_utf8_lossy(blob.content()).into_owned());
+        }
+        Ok(blobs.get(&id).cloned())
+    };
+    let mut revisions = Vec::new();
+
+    for oid in revwalk {
+        let commit = repo.find_commit(oid?)?;
+        let blob_id = blob_at(&commit, path)?;
+        let parent_ids = commit
+            .parents()
+            .map(|parent| blob_at(&parent, path))
+            .collect::<Result<Vec<_>, _>>()?;
+        let unchanged = if parent_ids.is_empty() {
+            blob_id.is_none()
+        } else {
+            parent_ids.contains(&blob_id)
+        };
+        if unchanged {
+            continue;
+        }
+
+        let author = commit.author_with_mailmap(&mailmap)?;
+        revisions.push(Revision {
+            commit: commit.id(),
+            author: Person::from_signature(&author),
+            committer: Person::from_signature(&commit.committer_with_mailmap(&mailmap)?),
+            seconds: author.when().seconds(),
+            contents: read(blob_id)?,
+            parents: parent_ids
+                .into_iter()
+                .map(&mut read)
+                .collect::<Result<_, _>>()?,
+        });
+    }
+
+    Ok(revisions)
+}
+
+/// The id of the blob at `path` in `commit`, if there is one.
+fn blob_at(commit: &Commit, path: &Path) -> Result<Option<Oid>, git2::Error> {
+    Ok(commit.tree()?.get_path(path).ok().map(|entry| entry.id()))
+}
+
+/// Returns every change to the version of `gem` across the lockfile's history.
+pub fn gem_history(
+    repo: &Repository,
+    path: &Path,
+    gem: &str,
+) -> Result<Vec<Transition>, git2::Error> {
+    let mut transitions = Vec::new();
+
+    for revision in revisions(repo, path, None)? {
+        let version = |contents: &Option<String>| {
+            contents
+                .as_deref()
+                .and_then(|contents| locked_version(contents, gem))
+        };
+        let to = version(&revision.contents);
+        let parents = revision.parents.iter().map(version).collect::<Vec<_>>();
+        if parents.contains(&to) || (parents.is_empty() && to.is_none()) {
+            continue;
+        }
+
+        transitions.push(Transition {
+            commit: revision.commit,
+            author: revision.author,
+            seconds: revision.seconds,
+            from: parents.into_iter().next().flatten(),
+            to,
+        });
+    }
+
+    Ok(transitions)
+}
+
+/// The version of `gem` resolved by a lockfile, where `bundler` and `ruby`
+/// also match the `BUNDLED WITH` and `RUBY VERSION` sections.
+///
+/// Platform variants are collapsed, and if they disagree every distinct
+/// version is listed.
+fn locked_version(contents: &str, gem: &str) -> Option<String> {
+    let entries = lockfile::parse(contents);
+    let mut versions = lockfile::versions(entries.iter().filter(|entry| entry.name == gem))
+        .into_values()
+        .collect::<Vec<_>>();
+    versions.sort();
+    versions.dedup();
+
+    if versions.is_empty() {
+        None
+    } else {
+        Some(versions.join(", "))
+    }
+}
+
+/// Dates every resolved spec, and the Bundler and Ruby versions, by the
+/// commit that last changed the version rather than the one that last
+/// touched its line.
+///
+/// Gems are matched across commits by name and platform, and a commit only
+/// counts as changing a version if none of its parents already had it.
+/// Commits in `ignored` never count; a version they introduced is dated by
+/// the change before it. Specs that never appear in a committed lockfile,
+/// and lines changed in the working tree, keep their blamed hunk.
+pub fn date_by_version(
+    repo: &Repository,
+    path: &Path,
+    start: Option<Oid>,
+    ignored: &HashSet<Oid>,
+    entries: &[Entry],
+    attribution: &mut Attribution,
+) -> Result<(), git2::Error> {
+    type Key = (String, Option<String>);
+    let mut changes: HashMap<(Key, String), Hunk> = HashMap::new();
+    let mut latest: HashMap<Key, Hunk> = HashMap::new();
+
+    for revision in revisions(repo, path, start)? {
+        if ignored.contains(&revision.commit) {
+            continue;
+        }
+        let parents = revision
+            .parents
+            .iter()
+            .map(|contents| versions(contents.as_deref()))
+            .collect::<Vec<_>>();
+        for (key, version) in versions(revision.contents.as_deref()) {
+            if parents
+                .iter()
+                .any(|parent| parent.get(&key) == Some(&version))
+            {
+                continue;
+            }
+            let hunk = Hunk {
+                commit: revision.commit,
+                author: revision.author.clone(),
+                committer: revision.committer.clone(),
+                seconds: revision.seconds,
+            };
+            latest.insert(key.clone(), hunk.clone());
+            changes.insert((key, version), hunk);
+        }
+    }
+
+    for entry in entries
+        .iter()
+        .filter(|entry| matches!(entry.kind, Kind::Spec | Kind::Runtime))
+    {
+        // A version changed in the working tree has no commit to date it by.
+        if attribution
+            .line(entry.line)
+            .is_some_and(|hunk| !hunk.is_committed())
+        {
+            continue;
+        }
+        let key = (entry.name.clone(), entry.platform.clone());
+        let version = entry.version.clone().unwrap_or_default();
+        let hunk = match changes.remove(&(key.clone(), version)) {
+            Some(hunk) => hunk,
+            None => match latest.get(&key) {
+                Some(hunk) => hunk.clone(),
+                None => continue,
+            },
+        };
+        attribution.reassign(entry.line, hunk);
+    }
+
+    Ok(())
+}
+
+/// The locked versions in a revision of the lockfile, if it existed.
+fn versions(contents: Option<&str>) -> BTreeMap<(String, Option<String>), String> {
+    lockfile::versions(&lockfile::parse(contents.unwrap_or_default()))
+}
+
+/// When a platform listed in the lockfile was added to it.
+pub struct PlatformAdded {
+    pub platform: String,
+    pub commit: Oid,
+    pub author: Person,
+    pub seconds: i64,
+}
+
+/// Finds the commit since which each platform of the committed lockfile has
+/// been listed under `PLATFORMS`, in lockfile order.
+///
+/// A platform counts as added by a commit when none of its parents list it,
+/// so a merge is never credited with a platform added on its branch. One
+/// that was removed and added back again dates from when it was last added.
+pub fn platforms_added(repo: &Repository, path: &Path) -> Result<Vec<PlatformAdded>, git2::Error> {
+    let mut added: HashMap<String, PlatformAdded> = HashMap::new();
+
+    for revision in revisions(repo, path, None)? {
+        let parents = revision
+            .parents
+            .iter()
+            .map(|contents| platforms(contents.as_deref()))
+            .collect::<Vec<_>>();
+        for platform in platforms(revision.contents.as_deref()) {
+            if parents.iter().any(|parent| parent.contains(&platform)) {
+                continue;
+            }
+            added.insert(
+                platform.clone(),
+                PlatformAdded {
+                    platform,
+                    commit: revision.commit,
+                    author: revision.author.clone(),
+                    seconds: revision.seconds,
+                },
+            );
+        }
+    }
+
+    let head = repo.head()?.peel_to_commit()?;
+    let contents = match blob_at(&head, path)? {
+        Some(id) => Some(String::from_utf8_lossy(repo.find_blob(id)?.content()).into_owned()),
+        None => None,
+    };
+    Ok(platforms(contents.as_deref())
+        .iter()
+        .filter_map(|platform| added.remove(platform))
+        .collect())
+}
+
+/// The platforms listed under `PLATFORMS`, in lockfile order.
+fn platforms(contents: Option<&str>) -> Vec<String> {
+    lockfile::parse(contents.unwrap_or_default())
+        .into_iter()
+        .filter(|entry| entry.kind == Kind::Platform)
+        .map(|entry| entry.name)
+        .collect()
+}
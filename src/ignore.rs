@@ -0,0 +1,135 @@
+use crate::blame::Attribution;
+use crate::lockfile::{self, Entry, Kind};
+use git2::{BlameOptions, Oid, Repository};
+use std::collections::{hash_map, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+/// Commits that should not count as the last change to a gem.
+#[derive(Default)]
+pub struct IgnoreRules {
+    pub revs: HashSet<Oid>,
+    /// Also pass over commits that touched a gem's line without changing its
+    /// version.
+    pub unchanged_versions: bool,
+}
+
+impl IgnoreRules {
+    /// Reads the commits listed in the file named by `blame.ignoreRevsFile`,
+    /// or else `.git-blame-ignore-revs` at the root of the working tree.
+    ///
+    /// Entries that don't resolve, such as commits only on other branches, are
+    /// skipped.
+    pub fn read_revs_file(repo: &Repository, workdir: &Path) -> HashSet<Oid> {
+        let name = repo
+            .config()
+            .and_then(|config| config.get_path("blame.ignoreRevsFile"))
+            .unwrap_or_else(|_| ".git-blame-ignore-revs".into());
+        let contents = match fs::read_to_string(workdir.join(name)) {
+            Ok(contents) => contents,
+            Err(_) => return HashSet::new(),
+        };
+
+        contents
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or("").trim())
+            .filter(|line| !line.is_empty())
+            .filter_map(|rev| repo.revparse_single(rev).ok())
+            .filter_map(|object| object.peel_to_commit().ok())
+            .map(|commit| commit.id())
+            .collect()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.revs.is_empty() && !self.unchanged_versions
+    }
+}
+
+/// Moves every spec line last touched by an ignored commit back to the commit
+/// before it that really changed the gem.
+///
+/// Gems are followed into the parent commit by name and platform, so this
+/// works even when the ignored commit re-sorted or re-indented the lockfile.
+pub fn reattribute(
+    repo: &Repository,
+    relative: &Path,
+    entries: &[Entry],
+    attribution: &mut Attribution,
+    rules: &IgnoreRules,
+) -> Result<(), git2::Error> {
+    if rules.is_empty() {
+        return Ok(());
+    }
+    let mut parents: HashMap<Oid, Option<(Vec<Entry>, Attribution)>> = HashMap::new();
+
+    for entry in entries.iter().filter(|entry| entry.kind == Kind::Spec) {
+        let mut hunk = match attribution.line(entry.line) {
+            Some(hunk) if hunk.is_committed() => hunk.clone(),
+            _ => continue,
+        };
+        let original = hunk.commit;
+        let mut version = entry.version.clone();
+
+        loop {
+            let parent = match repo.find_commit(hunk.commit)?.parent(0) {
+                Ok(parent) => parent.id(),
+                Err(_) => break,
+            };
+            if let hash_map::Entry::Vacant(vacant) = parents.entry(parent) {
+                vacant.insert(blame_at(repo, relative, parent)?);
+            }
+            let (parent_entries, parent_attribution) = match &parents[&parent] {
+                Some(blamed) => blamed,
+                None => break,
+            };
+            let previous = parent_entries.iter().find(|previous| {
+                previous.kind == Kind::Spec
+                    && previous.name == entry.name
+                    && previous.platform == entry.platform
+            });
+            // A commit that added the gem is always its real origin.
+            let previous = match previous {
+                Some(previous) => previous,
+                None => break,
+            };
+
+            let ignored = rules.revs.contains(&hunk.commit)
+                || (rules.unchanged_versions && previous.version == version);
+            if !ignored {
+                break;
+            }
+            hunk = match parent_attribution.line(previous.line) {
+                Some(hunk) => hunk.clone(),
+                None => break,
+            };
+            version = previous.version.clone();
+        }
+
+        if hunk.commit != original {
+            attribution.reassign(entry.line, hunk);
+        }
+    }
+
+    Ok(())
+}
+
+/// Parses and blames the lockfile as of `commit`, or `None` if it didn't
+/// exist yet.
+fn blame_at(
+    repo: &Repository,
+    relative: &Path,
+    commit: Oid,
+) -> Result<Option<(Vec<Entry>, Attribution)>, git2::Error> {
+    let tree = repo.find_commit(commit)?.tree()?;
+    let blob = match tree.get_path(relative) {
+        Ok(entry) => repo.find_blob(entry.id())?,
+        Err(_) => return Ok(None),
+    };
+    let entries = lockfile::parse(&String::from_utf8_lossy(blob.content()));
+
+    let mut options = BlameOptions::new();
+    options.newest_commit(commit);
+    let blame = repo.blame_file(relative, Some(&mut options))?;
+
+    Ok(Some((entries, Attribution::from_blame(repo, &blame)?)))
+}
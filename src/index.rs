@@ -0,0 +1,161 @@
+use crate::error::Error;
+use crate::lockfile;
+use chrono::DateTime;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A local copy of the RubyGems compact index: the `versions` file listing
+/// every gem, and the `info/<gem>` files detailing each one.
+pub struct CompactIndex {
+    root: PathBuf,
+    /// Unyanked versions of every gem in the `versions` file, with their
+    /// platforms.
+    versions: HashMap<String, Vec<String>>,
+}
+
+impl CompactIndex {
+    /// Reads the `versions` file under `root`. A mirror holding only `info`
+    /// files is also accepted.
+    pub fn open(root: &Path) -> Result<CompactIndex, Error> {
+        let path = root.join("versions");
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound && root.join("info").is_dir() => {
+                String::new()
+            }
+            Err(e) => return Err(Error::IndexUnreadable(path, e)),
+        };
+
+        Ok(CompactIndex {
+            root: root.to_path_buf(),
+            versions: parse_versions(&contents),
+        })
+    }
+
+    /// Every published version of `gem`, without platforms, or `None` if the
+    /// index doesn't know the gem.
+    ///
+    /// The gem's `info` file is preferred when present, since the `versions`
+    /// file of a partial mirror may lag behind it.
+    pub fn versions(&self, gem: &str) -> Result<Option<Vec<String>>, Error> {
+        let path = self.root.join("info").join(gem);
+        match fs::read_to_string(&path) {
+            Ok(contents) => Ok(Some(parse_info(&contents))),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(self
+                .versions
+                .get(gem)
+                .map(|versions| without_platforms(versions))),
+            Err(e) => Err(Error::IndexUnreadable(path, e)),
+        }
+    }
+}
+
+/// Parses lines like `rack 2.2.7,2.2.8,-2.2.9,3.0.0-java <md5>` following
+/// the `---` separator. A gem may appear on several lines as versions are
+/// appended, and a leading `-` marks a yanked version.
+fn parse_versions(contents: &str) -> HashMap<String, Vec<String>> {
+    let mut gems: HashMap<String, Vec<String>> = HashMap::new();
+    let body = contents
+        .split_once("---\n")
+        .map_or(contents, |(_, body)| body);
+
+    for line in body.lines() {
+        let mut fields = line.split(' ');
+        let (name, list) = match (fields.next(), fields.next()) {
+            (Some(name), Some(list)) => (name, list),
+            _ => continue,
+        };
+        let versions = gems.entry(name.to_string()).or_default();
+        for version in list.split(',') {
+            match version.strip_prefix('-') {
+                Some(yanked) => versions.retain(|version| version != yanked),
+                None => versions.push(version.to_string()),
+            }
+        }
+    }
+
+    gems
+}
+
+/// Parses the versions out of lines like
+/// `1.15.4-x86_64-linux racc:~> 1.4|checksum:...,ruby:>= 2.7`.
+fn parse_info(contents: &str) -> Vec<String> {
+    let versions = contents
+        .lines()
+        .filter(|line| *line != "---" && !line.is_empty())
+        .filter_map(|line| line.split(' ').next())
+        .collect::<Vec<_>>();
+
+    without_platforms(&versions)
+}
+
+/// Drops the platform from versions like `1.15.4-x86_64-linux`, keeping one
+/// of each version.
+fn without_platforms<S: AsRef<str>>(versions: &[S]) -> Vec<String> {
+    let mut plain: Vec<String> = Vec::new();
+    for version in versions {
+        let version = lockfile::split_platform(version.as_ref())
+            .0
+            .unwrap_or_default();
+        if !plain.contains(&version) {
+            plain.push(version);
+        }
+    }
+
+    plain
+}
+
+/// One published version of a gem.
+pub struct Release {
+    pub version: String,
+    pub prerelease: bool,
+    /// When the version was pushed to the gem server.
+    pub seconds: i64,
+}
+
+/// An entry of the RubyGems `/api/v1/versions/<gem>.json` endpoint.
+#[derive(Deserialize)]
+struct ApiVersion {
+    number: String,
+    #[serde(default)]
+    prerelease: bool,
+    created_at: String,
+}
+
+/// Reads every release of `gem` from a directory of versions files, as saved
+/// from `https://rubygems.org/api/v1/versions/<gem>.json`.
+///
+/// The compact index does not record when versions were released, so it
+/// can't be used to date them. Returns `None` when the directory has no file
+/// for the gem.
+pub fn releases(dir: &Path, gem: &str) -> Result<Option<Vec<Release>>, Error> {
+    let path = dir.join(format!("{}.json", gem));
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound && dir.is_dir() => return Ok(None),
+        Err(e) => return Err(Error::IndexUnreadable(path, e)),
+    };
+    let versions: Vec<ApiVersion> = serde_json::from_str(&contents)
+        .map_err(|e| Error::InvalidIndex(path.clone(), e.to_string()))?;
+
+    versions
+        .into_iter()
+        .map(|version| {
+            let created = DateTime::parse_from_rfc3339(&version.created_at).map_err(|_| {
+                Error::InvalidIndex(
+                    path.clone(),
+                    format!("`{}` is not a valid created_at date", version.created_at),
+                )
+            })?;
+            Ok(Release {
+                version: version.number,
+                prerelease: version.prerelease,
+                seconds: created.timestamp(),
+            })
+        })
+        .collect::<Result<_, Error>>()
+        .map(Some)
+}
@@ -0,0 +1,46 @@
+use crate::index::Release;
+use crate::version;
+
+const SECONDS_PER_YEAR: f64 = 365.25 * 24.0 * 60.0 * 60.0;
+
+/// How far a locked gem lags behind its newest release.
+pub struct Lag {
+    pub newest: String,
+    /// Years between the release of the locked version and the newest one.
+    pub years: f64,
+}
+
+/// Measures how far `locked` lags behind the newest stable release.
+///
+/// Prereleases only count as newest when the gem has never had a stable
+/// release. Returns `None` if the locked version is not among `releases`.
+pub fn lag(locked: &str, releases: &[Release]) -> Option<Lag> {
+    let released = |version: &str| {
+        releases
+            .iter()
+            .filter(|release| release.version == version)
+            .map(|release| release.seconds)
+            .min()
+    };
+    let locked_at = released(locked)?;
+
+    let stable = releases.iter().filter(|release| !release.prerelease);
+    let newest = match stable.clone().next() {
+        Some(_) => stable.max_by(|a, b| version::compare(&a.version, &b.version)),
+        None => releases
+            .iter()
+            .max_by(|a, b| version::compare(&a.version, &b.version)),
+    }?;
+    if version::compare(&newest.version, locked).is_lt() {
+        return Some(Lag {
+            newest: locked.to_string(),
+            years: 0.0,
+        });
+    }
+    let newest_at = released(&newest.version)?;
+
+    Some(Lag {
+        newest: newest.version.clone(),
+        years: (newest_at - locked_at).max(0) as f64 / SECONDS_PER_YEAR,
+    })
+}
@@ -0,0 +1,264 @@
+use regex::Regex;
+use std::collections::BTreeMap;
+
+/// Indentation of a resolved gem under a source's `specs:`.
+pub const SPEC_INDENT: usize = 4;
+
+/// Indentation of a dependency constraint nested under a resolved gem.
+pub const DEPENDENCY_INDENT: usize = 6;
+
+/// A top-level section of a Gemfile.lock, such as `GEM` or `PLATFORMS`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Section {
+    Gem,
+    Git,
+    Path,
+    Plugin,
+    Platforms,
+    Dependencies,
+    RubyVersion,
+    BundledWith,
+    Checksums,
+    Other(String),
+}
+
+impl Section {
+    fn from_header(header: &str) -> Section {
+        match header {
+            "GEM" => Section::Gem,
+            "GIT" => Section::Git,
+            "PATH" => Section::Path,
+            "PLUGIN SOURCE" => Section::Plugin,
+            "PLATFORMS" => Section::Platforms,
+            "DEPENDENCIES" => Section::Dependencies,
+            "RUBY VERSION" => Section::RubyVersion,
+            "BUNDLED WITH" => Section::BundledWith,
+            "CHECKSUMS" => Section::Checksums,
+            other => Section::Other(other.to_string()),
+        }
+    }
+
+    /// The section's header as written in the lockfile.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Section::Gem => "GEM",
+            Section::Git => "GIT",
+            Section::Path => "PATH",
+            Section::Plugin => "PLUGIN SOURCE",
+            Section::Platforms => "PLATFORMS",
+            Section::Dependencies => "DEPENDENCIES",
+            Section::RubyVersion => "RUBY VERSION",
+            Section::BundledWith => "BUNDLED WITH",
+            Section::Checksums => "CHECKSUMS",
+            Section::Other(header) => header,
+        }
+    }
+
+    /// Whether this section is a gem source with a `specs:` list.
+    pub fn is_source(&self) -> bool {
+        matches!(
+            self,
+            Section::Gem | Section::Git | Section::Path | Section::Plugin
+        )
+    }
+}
+
+/// The options of a `GEM`, `GIT`, `PATH` or `PLUGIN SOURCE` block, listed
+/// above its `specs:`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Source {
+    /// Zero-based line number of the block's header, which tells blocks of
+    /// the same kind apart.
+    pub line: usize,
+    /// Where gems come from: a gem server, a git URL or a local path. Older
+    /// lockfiles list several gem servers in one `GEM` block.
+    pub remotes: Vec<String>,
+    /// The commit a `GIT` source is pinned to.
+    pub revision: Option<String>,
+    /// Zero-based line number of the `revision:` line.
+    pub revision_line: Option<usize>,
+    pub branch: Option<String>,
+    pub tag: Option<String>,
+}
+
+/// Whether an entry is a resolved gem, one of its dependency constraints, or
+/// the Bundler or Ruby version the lockfile was resolved with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Kind {
+    Spec,
+    Constraint {
+        parent: String,
+    },
+    /// The version under `BUNDLED WITH`, named `bundler`, or under
+    /// `RUBY VERSION`, named `ruby`.
+    Runtime,
+    /// A platform listed under `PLATFORMS`, named after the platform.
+    Platform,
+}
+
+/// A `name (version)` line listed under a source's `specs:`, a platform under
+/// `PLATFORMS`, or the version line of `BUNDLED WITH` or `RUBY VERSION`.
+///
+/// Lines indented by four spaces are resolved gems and carry a version and
+/// optional platform. Lines indented by six spaces are the dependency
+/// constraints of the gem above them, and their `version` holds the
+/// requirement as written, e.g. `>= 1.2, < 2`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// Zero-based line number in the lockfile.
+    pub line: usize,
+    pub indent: usize,
+    pub kind: Kind,
+    pub name: String,
+    pub version: Option<String>,
+    pub platform: Option<String>,
+    pub section: Section,
+    pub source: Source,
+}
+
+/// Parses the contents of a Gemfile.lock into its spec, platform and runtime
+/// entries.
+pub fn parse(contents: &str) -> Vec<Entry> {
+    let re = Regex::new(r"^( +)([^\s(]+)(?: \(([^)]+)\))?!?$").unwrap();
+    let mut entries = Vec::new();
+    let mut section = Section::Other(String::new());
+    let mut in_specs = false;
+    let mut parent: Option<String> = None;
+    let mut source = Source::default();
+
+    for (i, line) in contents.lines().enumerate() {
+        if line.is_empty() {
+            continue;
+        }
+        if !line.starts_with(' ') {
+            section = Section::from_header(line.trim_end());
+            in_specs = false;
+            parent = None;
+            source = Source {
+                line: i,
+                ..Source::default()
+            };
+            continue;
+        }
+        if section == Section::Platforms {
+            entries.push(Entry {
+                line: i,
+                indent: line.len() - line.trim_start().len(),
+                kind: Kind::Platform,
+                name: line.trim().to_string(),
+                version: None,
+                platform: None,
+                section: section.clone(),
+                source: Source::default(),
+            });
+            continue;
+        }
+        let runtime = match section {
+            Section::BundledWith => Some(("bundler", line.trim())),
+            Section::RubyVersion => line
+                .trim()
+                .strip_prefix("ruby ")
+                .and_then(|version| version.split_whitespace().next())
+                .map(|version| ("ruby", version)),
+            _ => None,
+        };
+        if let Some((name, version)) = runtime {
+            entries.push(Entry {
+                line: i,
+                indent: line.len() - line.trim_start().len(),
+                kind: Kind::Runtime,
+                name: name.to_string(),
+                version: Some(version.to_string()),
+                platform: None,
+                section: section.clone(),
+                source: Source::default(),
+            });
+            continue;
+        }
+        if !section.is_source() {
+            continue;
+        }
+        if line.trim_end() == "  specs:" {
+            in_specs = true;
+            continue;
+        }
+        if !in_specs {
+            if let Some((key, value)) = line.trim().split_once(": ") {
+                let value = value.to_string();
+                match key {
+                    "remote" => source.remotes.push(value),
+                    "revision" => {
+                        source.revision = Some(value);
+                        source.revision_line = Some(i);
+                    }
+                    "branch" => source.branch = Some(value),
+                    "tag" => source.tag = Some(value),
+                    _ => {}
+                }
+            }
+            continue;
+        }
+
+        let captures = match re.captures(line) {
+            Some(captures) => captures,
+            None => continue,
+        };
+        let indent = captures[1].len();
+        let name = captures[2].to_string();
+        let kind = match (indent, &parent) {
+            (SPEC_INDENT, _) => {
+                parent = Some(name.clone());
+                Kind::Spec
+            }
+            (DEPENDENCY_INDENT, Some(parent)) => Kind::Constraint {
+                parent: parent.clone(),
+            },
+            _ => continue,
+        };
+        let raw_version = captures.get(3).map(|m| m.as_str());
+        let (version, platform) = match (raw_version, &kind) {
+            (Some(raw), Kind::Spec) => split_platform(raw),
+            (Some(raw), _) => (Some(raw.to_string()), None),
+            (None, _) => (None, None),
+        };
+
+        entries.push(Entry {
+            line: i,
+            indent,
+            kind,
+            name,
+            version,
+            platform,
+            section: section.clone(),
+            source: source.clone(),
+        });
+    }
+
+    entries
+}
+
+/// The version of every resolved spec and runtime among `entries`, keyed by
+/// name and platform. Entries without a version are left out.
+pub fn versions<'a>(
+    entries: impl IntoIterator<Item = &'a Entry>,
+) -> BTreeMap<(String, Option<String>), String> {
+    entries
+        .into_iter()
+        .filter(|entry| matches!(entry.kind, Kind::Spec | Kind::Runtime))
+        .filter_map(|entry| {
+            let version = entry.version.clone()?;
+            Some(((entry.name.clone(), entry.platform.clone()), version))
+        })
+        .collect()
+}
+
+/// Splits `1.15.4-x86_64-linux` into its version and platform.
+///
+/// RubyGems versions never contain a dash, so everything after the first one
+/// is the platform.
+pub(crate) fn split_platform(raw: &str) -> (Option<String>, Option<String>) {
+    match raw.split_once('-') {
+        Some((version, platform)) => (Some(version.to_string()), Some(platform.to_string())),
+        None => (Some(raw.to_string()), None),
+    }
+}
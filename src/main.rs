@@ -1,95 +1,1326 @@
-use chrono::{Local, TimeZone};
-use clap::Parser;
-use git2::Repository;
-use regex::Regex;
-use std::collections::{BTreeMap, HashSet};
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use advisory::Advisory;
+use blame::{Attribution, Person};
+use chrono::{SecondsFormat, TimeZone, Utc};
+use clap::{Parser, Subcommand, ValueEnum};
+use dates::{Dates, Granularity, Zone};
+use diff::Change;
+use error::{Error, EXIT_MAX_AGE, EXIT_VULNERABLE, EXIT_WARN_AGE};
+use git2::{BlameOptions, Commit, ErrorCode};
+use ignore::IgnoreRules;
+use index::CompactIndex;
+use lockfile::{Section, Source};
+use project::Project;
+use report::{Analysis, Record};
+use serde::Serialize;
+use staleness::Age;
+use std::collections::{hash_map, BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io;
 use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use version::Version;
 
-fn main() {
+mod advisory;
+mod blame;
+mod dates;
+mod diff;
+mod error;
+mod history;
+mod ignore;
+mod index;
+mod libyear;
+mod lockfile;
+mod project;
+mod report;
+mod staleness;
+mod version;
+
+const GEMFILE_LOCK: &str = "Gemfile.lock";
+
+fn main() -> ExitCode {
     let cli = Cli::parse();
-    let project_dir = cli.directory;
-    if let Err(e) = run(project_dir) {
-        println!("Error: {}", e);
+    match run(cli) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::from(e.exit_code())
+        }
     }
 }
 
 /// A program to see when you last updated your specs in your Gemfile.lock.
 #[derive(Parser)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// The directory of the bundler project you want to check.
+    #[arg(default_value = ".")]
     directory: String,
+
+    /// Also report changes to the dependency constraints declared by each gem.
+    #[arg(long)]
+    constraints: bool,
+
+    /// Show who last changed each gem.
+    #[arg(long)]
+    authors: bool,
+
+    /// How to group the report.
+    #[arg(long, value_enum, default_value_t = Grouping::Date)]
+    by: Grouping,
+
+    /// How finely to group gems when grouping by date.
+    #[arg(long, value_enum, default_value_t = Granularity::Day)]
+    group: Granularity,
+
+    /// Show dates in this time zone: utc, local or an IANA name such as
+    /// Europe/Berlin.
+    #[arg(long, global = true, value_name = "ZONE", default_value = "local")]
+    tz: Zone,
+
+    /// A strftime format for dates.
+    #[arg(
+        long,
+        global = true,
+        value_name = "FORMAT",
+        default_value = "%Y-%m-%d",
+        value_parser = dates::parse_format
+    )]
+    date_format: String,
+
+    /// Also show how long ago each date was, e.g. "3 months ago".
+    #[arg(long, global = true)]
+    relative: bool,
+
+    /// How to order gems when grouping by gem.
+    #[arg(long, value_enum, default_value_t = SortOrder::Name)]
+    sort: SortOrder,
+
+    /// The output format.
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+
+    /// Fail if any gem has not been updated in this long, e.g. 180d, 26w or 1y.
+    #[arg(long, value_name = "AGE")]
+    max_age: Option<Age>,
+
+    /// Warn if any gem has not been updated in this long, e.g. 90d.
+    #[arg(long, value_name = "AGE")]
+    warn_age: Option<Age>,
+
+    /// Check every committed lockfile at or below the directory.
+    #[arg(long)]
+    recursive: bool,
+
+    /// Analyze the lockfile as of this commit, branch or tag instead of the
+    /// working tree.
+    #[arg(long, value_name = "REF")]
+    rev: Option<String>,
+
+    /// Don't count this commit as the last change to any gem. May be given
+    /// more than once, and adds to `.git-blame-ignore-revs`.
+    #[arg(long, value_name = "REF")]
+    ignore_rev: Vec<String>,
+
+    /// Skip commits that touched a gem's line without changing its version.
+    #[arg(long)]
+    ignore_unchanged_versions: bool,
+
+    /// How to decide when a gem was last updated.
+    #[arg(long, value_enum, default_value_t = Dating::Blame)]
+    dating: Dating,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Show every version of a gem the lockfile has pinned.
+    History {
+        /// The name of the gem.
+        gem: String,
+
+        /// The directory of the bundler project you want to check.
+        #[arg(default_value = ".")]
+        directory: String,
+    },
+    /// List the gems that changed between two revisions.
+    Diff {
+        /// The revisions to compare, as `OLD..NEW`, or `OLD...NEW` to compare
+        /// NEW against its merge base with OLD. Either side defaults to HEAD,
+        /// and a single revision is compared against HEAD.
+        range: String,
+
+        /// The directory of the bundler project you want to check.
+        #[arg(default_value = ".")]
+        directory: String,
+    },
+    /// Measure how many years each gem lags behind its newest release.
+    Libyear {
+        /// A directory of `<gem>.json` files saved from the RubyGems
+        /// `/api/v1/versions/<gem>.json` endpoint.
+        #[arg(long, value_name = "DIR")]
+        releases: PathBuf,
+
+        /// The directory of the bundler project you want to check.
+        #[arg(default_value = ".")]
+        directory: String,
+    },
+    /// Compare each locked gem with the newest version in a local copy of the
+    /// RubyGems compact index.
+    Outdated {
+        /// A directory holding the compact index's `versions` file and
+        /// `info/<gem>` files.
+        #[arg(long, value_name = "DIR")]
+        index: PathBuf,
+
+        /// The directory of the bundler project you want to check.
+        #[arg(default_value = ".")]
+        directory: String,
+    },
+    /// Check locked gems against a local ruby-advisory-db checkout.
+    Audit {
+        /// The root of a ruby-advisory-db checkout, holding `gems/<gem>/*.yml`.
+        #[arg(long, value_name = "DIR")]
+        advisory_db: PathBuf,
+
+        /// The directory of the bundler project you want to check.
+        #[arg(default_value = ".")]
+        directory: String,
+    },
+    /// List the locked platforms, when each was added, and the gems locked
+    /// per platform.
+    Platforms {
+        /// The directory of the bundler project you want to check.
+        #[arg(default_value = ".")]
+        directory: String,
+    },
+    /// Count gems by how long ago they were last updated.
+    Summary {
+        /// The directory of the bundler project you want to check.
+        #[arg(default_value = ".")]
+        directory: String,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Grouping {
+    /// One block per day, listing the specs last touched that day.
+    Date,
+    /// One row per gem with its version and when it was last touched.
+    Gem,
+    /// One block per author, listing the gems they last touched.
+    Author,
+    /// One block per GEM, GIT or PATH source, listing the gems it provides.
+    Source,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Dating {
+    /// The last commit to touch the gem's line, from git blame.
+    Blame,
+    /// The last commit to change the gem's version, from the lockfile's
+    /// history.
+    Version,
 }
 
-fn run(project_dir: String) -> Result<(), Box<dyn std::error::Error>> {
-    let project_path = Path::new(&project_dir);
-    let gemfile_lock = "Gemfile.lock";
-    let gemfile_lock_path = Path::new(gemfile_lock);
-    let git_dir = ".git";
-    let git_path = project_path.join(git_dir);
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    /// Human-readable text.
+    Text,
+    /// A JSON document listing every resolved gem.
+    Json,
+}
 
-    let (spec_lines, gemfile_lock_lines) = get_spec_lines(project_path.join(gemfile_lock));
+#[derive(Clone, Copy, ValueEnum)]
+enum SortOrder {
+    /// Alphabetically by gem name.
+    Name,
+    /// Least recently touched first.
+    Age,
+}
 
-    let repo = Repository::open(git_path).unwrap();
-    // Instead of `None` you can also pass a `git2::BlameOptions` object.
-    let blame = repo.blame_file(gemfile_lock_path, None).unwrap();
-    let hunks = blame.iter().collect::<Vec<_>>();
-    let mut map: BTreeMap<String, Vec<String>> = BTreeMap::new();
+fn run(cli: Cli) -> Result<ExitCode, Error> {
+    let now = Utc::now().timestamp();
+    let dates = Dates {
+        zone: cli.tz,
+        format: cli.date_format.clone(),
+        relative: cli.relative,
+        now,
+    };
 
-    for hunk in hunks {
-        let seconds = hunk.final_signature().when().seconds();
-        let formatted_time = format_seconds(seconds);
+    match &cli.command {
+        Some(Command::History { gem, directory }) => {
+            run_history(gem, directory, &dates)?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        Some(Command::Diff { range, directory }) => {
+            run_diff(range, directory)?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        Some(Command::Libyear {
+            releases,
+            directory,
+        }) => {
+            run_libyear(releases, directory)?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        Some(Command::Outdated { index, directory }) => {
+            run_outdated(index, directory, &dates)?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        Some(Command::Audit {
+            advisory_db,
+            directory,
+        }) => return run_audit(advisory_db, directory, &dates),
+        Some(Command::Platforms { directory }) => {
+            run_platforms(directory, &dates)?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        Some(Command::Summary { directory }) => {
+            run_summary(directory, &dates)?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        None => {}
+    }
 
-        map.entry(formatted_time)
-            .and_modify(|lines| {
-                let start_line = hunk.final_start_line() - 1 as usize;
-                let end_line = start_line + hunk.lines_in_hunk() as usize;
+    let project = Project::discover(Path::new(&cli.directory), GEMFILE_LOCK)?;
+    let rev = cli
+        .rev
+        .as_deref()
+        .map(|rev| project.resolve(rev))
+        .transpose()?;
+    let mut ignore = IgnoreRules {
+        revs: IgnoreRules::read_revs_file(&project.repo, &project.workdir),
+        unchanged_versions: cli.ignore_unchanged_versions,
+    };
+    for rev in &cli.ignore_rev {
+        ignore.revs.insert(project.resolve(rev)?.id());
+    }
+    let lockfiles = if cli.recursive {
+        project.tracked_lockfiles(rev.as_ref())?
+    } else {
+        vec![project.relative.clone()]
+    };
+    if lockfiles.is_empty() {
+        return Err(Error::LockfileNotFound(project.lockfile()));
+    }
+    let analyses = lockfiles
+        .into_iter()
+        .map(|relative| analyze(&project, relative, rev.as_ref(), &ignore, cli.dating))
+        .collect::<Result<Vec<_>, Error>>()?;
 
-                let mut current_line = start_line;
-                while current_line < end_line {
-                    if spec_lines.contains(&current_line) {
-                        let line = gemfile_lock_lines[current_line].clone();
-                        lines.push(line);
+    match cli.format {
+        Format::Json => print_json(&analyses, cli.recursive)?,
+        Format::Text => {
+            for (i, analysis) in analyses.iter().enumerate() {
+                if cli.recursive {
+                    if i > 0 {
+                        println!();
+                    }
+                    println!("== {} ==", analysis.path.display());
+                }
+                match cli.by {
+                    Grouping::Date => {
+                        let spec_lines =
+                            get_spec_lines(&analysis.contents, &analysis.entries, cli.constraints);
+                        print_by_date(
+                            &analysis.attribution,
+                            &spec_lines,
+                            cli.authors,
+                            &dates,
+                            cli.group,
+                        )?;
+                    }
+                    Grouping::Gem => print_by_gem(
+                        analysis.records(),
+                        analysis.runtimes(),
+                        cli.sort,
+                        cli.authors,
+                        &dates,
+                    )?,
+                    Grouping::Author => {
+                        print_by_author(analysis.records(), analysis.runtimes(), &dates)?
+                    }
+                    Grouping::Source => {
+                        print_by_source(analysis.records(), analysis.runtimes(), &dates)?
                     }
-                    current_line += 1;
                 }
+            }
+            if cli.recursive {
+                print_shared(&analyses, &dates)?;
+            }
+        }
+    }
+
+    if cli.max_age.is_none() && cli.warn_age.is_none() {
+        return Ok(ExitCode::SUCCESS);
+    }
+    let mut failed = Vec::new();
+    let mut warned = Vec::new();
+    for analysis in &analyses {
+        let gems = report::latest_per_gem(analysis.records());
+        let staleness = staleness::check(&gems, now, cli.max_age, cli.warn_age);
+        let label = |record: &Record| stale_line(record, &analysis.path, cli.recursive, &dates);
+        for record in staleness.failed {
+            failed.push(label(record)?);
+        }
+        for record in staleness.warned {
+            warned.push(label(record)?);
+        }
+    }
+    if let Some(max_age) = cli.max_age {
+        print_stale("--max-age", max_age, &failed);
+    }
+    if let Some(warn_age) = cli.warn_age {
+        print_stale("--warn-age", warn_age, &warned);
+    }
+
+    if !failed.is_empty() {
+        Ok(ExitCode::from(EXIT_MAX_AGE))
+    } else if !warned.is_empty() {
+        Ok(ExitCode::from(EXIT_WARN_AGE))
+    } else {
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+/// Reads and blames the lockfile at `relative` within the project.
+///
+/// Without a revision the lockfile is read from the working tree and its
+/// uncommitted lines are left to "Not Committed Yet"; with one, the lockfile
+/// and its blame both come from that commit.
+fn analyze(
+    project: &Project,
+    relative: PathBuf,
+    rev: Option<&Commit>,
+    ignore: &IgnoreRules,
+    dating: Dating,
+) -> Result<Analysis, Error> {
+    let path = project.workdir.join(&relative);
+    let repo = &project.repo;
+    let mut options = BlameOptions::new();
+
+    let contents = match rev {
+        Some(commit) => {
+            options.newest_commit(commit.id());
+            project.read_at(commit, &relative)?
+        }
+        None => {
+            // A repository without commits can't have tracked the lockfile yet.
+            if repo.head().is_err() {
+                return Err(Error::Untracked(path));
+            }
+            read_lockfile(&path)?
+        }
+    };
+    let entries = lockfile::parse(&contents);
+
+    let blame = repo
+        .blame_file(&relative, Some(&mut options))
+        .map_err(|e| match e.code() {
+            ErrorCode::NotFound => Error::Untracked(path.clone()),
+            _ => Error::Git(e),
+        })?;
+    let mut attribution = Attribution::from_blame(repo, &blame)?;
+    if rev.is_none() {
+        let head = repo.head()?.peel_to_commit()?;
+        let committed = project.read_at(&head, &relative)?;
+        attribution = attribution.onto_buffer(&committed, &contents)?;
+    }
+    match dating {
+        Dating::Blame => ignore::reattribute(repo, &relative, &entries, &mut attribution, ignore)?,
+        Dating::Version => history::date_by_version(
+            repo,
+            &relative,
+            rev.map(|commit| commit.id()),
+            &ignore.revs,
+            &entries,
+            &mut attribution,
+        )?,
+    }
+
+    Ok(Analysis {
+        path: relative,
+        contents,
+        entries,
+        attribution,
+    })
+}
+
+/// Analyzes the working tree's lockfile in `directory` with the default
+/// options, for the subcommands that build on the main report.
+fn analyze_directory(directory: &str) -> Result<Analysis, Error> {
+    let project = Project::discover(Path::new(directory), GEMFILE_LOCK)?;
+    let ignore = IgnoreRules {
+        revs: IgnoreRules::read_revs_file(&project.repo, &project.workdir),
+        unchanged_versions: false,
+    };
+    analyze(
+        &project,
+        project.relative.clone(),
+        None,
+        &ignore,
+        Dating::Blame,
+    )
+}
+
+fn stale_line(
+    record: &Record,
+    path: &Path,
+    recursive: bool,
+    dates: &Dates,
+) -> Result<String, Error> {
+    let mut line = format!(
+        "    {} ({}) last updated {}, {} days ago",
+        record.name,
+        record.version,
+        dates.format(record.seconds)?,
+        staleness::days_since(record.seconds, dates.now),
+    );
+    if recursive {
+        line.push_str(&format!(" in {}", path.display()));
+    }
+    Ok(line)
+}
+
+/// Summarizes the gems past a threshold on stderr, so JSON output stays valid.
+fn print_stale(flag: &str, age: Age, lines: &[String]) {
+    if lines.is_empty() {
+        return;
+    }
+    let noun = if lines.len() == 1 { "gem" } else { "gems" };
+    eprintln!("{} {} older than {} {}:", lines.len(), noun, flag, age);
+    for line in lines {
+        eprintln!("{}", line);
+    }
+}
+
+/// Lists gems locked at different versions across lockfiles, marking the
+/// lockfile furthest behind on each.
+fn print_shared(analyses: &[Analysis], dates: &Dates) -> Result<(), Error> {
+    let shared = report::shared_gems(analyses);
+    if shared.is_empty() {
+        return Ok(());
+    }
+
+    println!();
+    println!("Shared gems at different versions:");
+    for (name, locked) in shared {
+        println!("{}", name);
+        let path_width = locked
+            .iter()
+            .map(|(path, _)| path.display().to_string().len())
+            .max()
+            .unwrap_or(0);
+        let version_width = locked
+            .iter()
+            .map(|(_, record)| record.version.len())
+            .max()
+            .unwrap_or(0);
+        for (i, (path, record)) in locked.iter().enumerate() {
+            let marker = if i == 0 { "  furthest behind" } else { "" };
+            println!(
+                "    {:path_width$}  {:version_width$}  {}{}",
+                path.display().to_string(),
+                record.version,
+                dates.format(record.seconds)?,
+                marker,
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn run_history(gem: &str, directory: &str, dates: &Dates) -> Result<(), Error> {
+    let project = Project::discover(Path::new(directory), GEMFILE_LOCK)?;
+    // A repository without commits has no history to walk.
+    if project.repo.head().is_err() {
+        return Err(Error::Untracked(project.lockfile()));
+    }
+    let transitions = history::gem_history(&project.repo, &project.relative, gem)?;
+
+    if transitions.is_empty() {
+        println!("{} has never been in {}", gem, project.relative.display());
+    }
+    for transition in transitions {
+        let change = match (transition.from, transition.to) {
+            (None, Some(to)) => format!("added {}", to),
+            (Some(from), None) => format!("removed {}", from),
+            (Some(from), Some(to)) => format!("{} -> {}", from, to),
+            (None, None) => continue,
+        };
+        println!(
+            "{}  {}  {}  {}",
+            dates.format(transition.seconds)?,
+            &transition.commit.to_string()[..7],
+            transition.author.name,
+            change,
+        );
+    }
+
+    Ok(())
+}
+
+fn run_diff(range: &str, directory: &str) -> Result<(), Error> {
+    let project = Project::discover(Path::new(directory), GEMFILE_LOCK)?;
+    let resolve = |rev: &str| project.resolve(if rev.is_empty() { "HEAD" } else { rev });
+    let (old, new) = match range.split_once("...") {
+        // Like `git diff A...B`, compare B against where it forked from A.
+        Some((old, new)) => {
+            let new = resolve(new)?;
+            let base = project.repo.merge_base(resolve(old)?.id(), new.id())?;
+            (project.repo.find_commit(base)?, new)
+        }
+        None => {
+            let (old, new) = range.split_once("..").unwrap_or((range, "HEAD"));
+            (resolve(old)?, resolve(new)?)
+        }
+    };
+
+    let changes = diff::diff(
+        &project.read_at(&old, &project.relative)?,
+        &project.read_at(&new, &project.relative)?,
+    );
+    if changes.is_empty() {
+        println!("No gems changed");
+    }
+
+    let mut added = Vec::new();
+    let mut removed = Vec::new();
+    let mut upgraded = Vec::new();
+    let mut downgraded = Vec::new();
+    for (name, platform, change) in changes {
+        let on = platform.map_or(String::new(), |platform| format!(" on {}", platform));
+        match change {
+            Change::Added(version) => added.push(format!("{} {}{}", name, version, on)),
+            Change::Removed(version) => removed.push(format!("{} {}{}", name, version, on)),
+            Change::Upgraded(from, to, level) => upgraded.push(format!(
+                "{} {} -> {}{} ({})",
+                name,
+                from,
+                to,
+                on,
+                level.as_str()
+            )),
+            Change::Downgraded(from, to, level) => downgraded.push(format!(
+                "{} {} -> {}{} ({})",
+                name,
+                from,
+                to,
+                on,
+                level.as_str()
+            )),
+        }
+    }
+
+    let sections = [
+        ("Added", added),
+        ("Removed", removed),
+        ("Upgraded", upgraded),
+        ("Downgraded", downgraded),
+    ];
+    for (title, lines) in sections {
+        if lines.is_empty() {
+            continue;
+        }
+        println!("{}:", title);
+        for line in lines {
+            println!("    {}", line);
+        }
+    }
+
+    Ok(())
+}
+
+fn run_libyear(releases: &Path, directory: &str) -> Result<(), Error> {
+    let contents = read_lockfile(&Path::new(directory).join(GEMFILE_LOCK))?;
+    let mut rows = Vec::new();
+    let mut total = 0.0;
+    for (name, locked) in rubygems_versions(&contents) {
+        let lag = index::releases(releases, &name)?
+            .map(|releases| {
+                libyear::lag(&locked, &releases).ok_or("locked version not in releases")
             })
-            .or_insert(vec![]);
+            .unwrap_or(Err("not in index"));
+        if let Ok(lag) = &lag {
+            total += lag.years;
+        }
+        rows.push((name, locked, lag));
+    }
+
+    let name_width = rows.iter().map(|(name, ..)| name.len()).max().unwrap_or(0);
+    let version_width = rows
+        .iter()
+        .map(|(_, locked, _)| locked.len())
+        .max()
+        .unwrap_or(0);
+    let newest_width = rows
+        .iter()
+        .filter_map(|(.., lag)| lag.as_ref().ok().map(|lag| lag.newest.len()))
+        .max()
+        .unwrap_or(0);
+    for (name, locked, lag) in &rows {
+        match lag {
+            Ok(lag) => println!(
+                "{:name_width$}  {:version_width$}  {:newest_width$}  {:.2}",
+                name, locked, lag.newest, lag.years,
+            ),
+            Err(reason) => println!(
+                "{:name_width$}  {:version_width$}  {}",
+                name, locked, reason
+            ),
+        }
+    }
+    println!("Total: {:.2} libyears", total);
+
+    Ok(())
+}
+
+/// The version of every gem locked from a rubygems source, taking the newest
+/// of any platform variants.
+fn rubygems_versions(contents: &str) -> BTreeMap<String, String> {
+    let entries = lockfile::parse(contents);
+    let mut versions: BTreeMap<String, String> = BTreeMap::new();
+    for ((name, _), version) in
+        lockfile::versions(entries.iter().filter(|entry| entry.section == Section::Gem))
+    {
+        match versions.get(&name) {
+            Some(existing) if version::compare(existing, &version).is_ge() => {}
+            _ => {
+                versions.insert(name, version);
+            }
+        }
+    }
+
+    versions
+}
+
+fn run_outdated(index: &Path, directory: &str, dates: &Dates) -> Result<(), Error> {
+    let index = CompactIndex::open(index)?;
+    let analysis = analyze_directory(directory)?;
+
+    let mut rows = Vec::new();
+    for record in report::latest_per_gem(analysis.records()) {
+        if record.section != Section::Gem {
+            continue;
+        }
+        let newest = index.versions(&record.name)?.and_then(|versions| {
+            versions
+                .into_iter()
+                .filter(|version| !version::is_prerelease(version))
+                .max_by(|a, b| version::compare(a, b))
+        });
+        let status = match &newest {
+            None => "unknown",
+            Some(newest) if version::compare(newest, &record.version).is_le() => "up to date",
+            Some(newest) => {
+                version::level(&record.version, newest).map_or("", |level| level.as_str())
+            }
+        };
+        rows.push((record, newest.unwrap_or_default(), status));
+    }
+
+    let name_width = rows
+        .iter()
+        .map(|(record, ..)| record.name.len())
+        .max()
+        .unwrap_or(0);
+    let version_width = rows
+        .iter()
+        .map(|(record, ..)| record.version.len())
+        .max()
+        .unwrap_or(0);
+    let newest_width = rows
+        .iter()
+        .map(|(_, newest, _)| newest.len())
+        .max()
+        .unwrap_or(0);
+    let status_width = rows
+        .iter()
+        .map(|(.., status)| status.len())
+        .max()
+        .unwrap_or(0);
+    for (record, newest, status) in rows {
+        println!(
+            "{:name_width$}  {:version_width$}  {:newest_width$}  {:status_width$}  last updated {}",
+            record.name,
+            record.version,
+            newest,
+            status,
+            dates.format(record.seconds)?,
+        );
+    }
+
+    Ok(())
+}
+
+/// Lists the advisories affecting each locked gem and how long the vulnerable
+/// version has been locked.
+///
+/// Every platform variant is checked on its own, since variants may be locked
+/// at different versions.
+fn run_audit(advisory_db: &Path, directory: &str, dates: &Dates) -> Result<ExitCode, Error> {
+    let analysis = analyze_directory(directory)?;
+    let mut by_gem: HashMap<String, Vec<Advisory>> = HashMap::new();
+    let mut vulnerable_gems = HashSet::new();
+    let mut vulnerabilities = 0;
+
+    for record in analysis.records() {
+        let version = match record.version.parse::<Version>() {
+            Ok(version) => version,
+            Err(_) => continue,
+        };
+        let advisories = match by_gem.entry(record.name.clone()) {
+            hash_map::Entry::Occupied(entry) => entry.into_mut(),
+            hash_map::Entry::Vacant(entry) => {
+                entry.insert(advisory::for_gem(advisory_db, &record.name)?)
+            }
+        };
+        let advisories = advisories
+            .iter()
+            .filter(|advisory| advisory.affects(&version))
+            .collect::<Vec<_>>();
+        if advisories.is_empty() {
+            continue;
+        }
+        vulnerable_gems.insert(record.name.clone());
+        vulnerabilities += advisories.len();
+        let locked = match &record.platform {
+            Some(platform) => format!("{}-{}", record.version, platform),
+            None => record.version.clone(),
+        };
+
+        for advisory in advisories {
+            println!(
+                "{} ({}) {}: {}",
+                record.name,
+                locked,
+                advisory.id(),
+                advisory.title
+            );
+            println!(
+                "    vulnerable since {}, {} days",
+                dates.format(record.seconds)?,
+                staleness::days_since(record.seconds, dates.now),
+            );
+            match advisory.patched_versions() {
+                [] => println!("    no patched version"),
+                patched => println!("    patched in {}", patched.join("; ")),
+            }
+            if let Some(url) = &advisory.url {
+                println!("    {}", url);
+            }
+        }
+    }
+
+    if vulnerabilities == 0 {
+        println!("No vulnerabilities found");
+        return Ok(ExitCode::SUCCESS);
+    }
+    let noun = if vulnerabilities == 1 {
+        "vulnerability"
+    } else {
+        "vulnerabilities"
+    };
+    let gems = if vulnerable_gems.len() == 1 {
+        "gem"
+    } else {
+        "gems"
+    };
+    println!(
+        "{} {} in {} {}",
+        vulnerabilities,
+        noun,
+        vulnerable_gems.len(),
+        gems
+    );
+
+    Ok(ExitCode::from(EXIT_VULNERABLE))
+}
+
+/// Shows when each platform was added and which gems have platform variants,
+/// flagging variants locked at different versions.
+fn run_platforms(directory: &str, dates: &Dates) -> Result<(), Error> {
+    let project = Project::discover(Path::new(directory), GEMFILE_LOCK)?;
+    let path = project.lockfile();
+    if project.repo.head().is_err() {
+        return Err(Error::Untracked(path));
     }
+    let entries = lockfile::parse(&read_lockfile(&path)?);
+    let mut added = history::platforms_added(&project.repo, &project.relative)?;
 
-    for (time, lines) in &mut map {
-        if lines.len() > 0 {
-            println!("Updated {}:", time);
-            for line in lines {
-                println!("{}", line);
+    let platforms = entries
+        .iter()
+        .filter(|entry| entry.kind == lockfile::Kind::Platform)
+        .map(|entry| entry.name.as_str())
+        .collect::<Vec<_>>();
+    let width = platforms.iter().map(|name| name.len()).max().unwrap_or(0);
+    println!("Platforms:");
+    for platform in platforms {
+        match added.iter().position(|added| added.platform == platform) {
+            Some(i) => {
+                let added = added.remove(i);
+                println!(
+                    "    {:width$}  added {}  {}  {}",
+                    platform,
+                    dates.format(added.seconds)?,
+                    &added.commit.to_string()[..7],
+                    added.author.name,
+                );
             }
+            None => println!("    {:width$}  not committed", platform),
+        }
+    }
+
+    let variants = report::platform_variants(&entries)
+        .into_iter()
+        .filter(|(_, variants)| variants.len() > 1 || variants[0].0 != "ruby")
+        .collect::<Vec<_>>();
+    if variants.is_empty() {
+        return Ok(());
+    }
+    println!("Platform variants:");
+    for (name, variants) in variants {
+        let version = &variants[0].1;
+        if variants.iter().all(|(_, other)| other == version) {
+            let platforms = variants
+                .iter()
+                .map(|(platform, _)| platform.as_str())
+                .collect::<Vec<_>>();
+            println!("    {} ({}) {}", name, version, platforms.join(", "));
+        } else {
+            let locked = variants
+                .iter()
+                .map(|(platform, version)| format!("{} on {}", version, platform))
+                .collect::<Vec<_>>();
+            println!("    {} {}  versions differ", name, locked.join(", "));
         }
     }
 
     Ok(())
 }
 
-fn format_seconds(seconds: i64) -> String {
-    let local_time = Local.timestamp_opt(seconds, 0).unwrap();
-    local_time.format("%Y-%m-%d").to_string()
+fn run_summary(directory: &str, dates: &Dates) -> Result<(), Error> {
+    let analysis = analyze_directory(directory)?;
+    let gems = report::latest_per_gem(analysis.records());
+    let summary = staleness::summarize(&gems, dates.now);
+
+    let noun = if gems.len() == 1 { "gem" } else { "gems" };
+    println!("{} {} last updated:", gems.len(), noun);
+    let labels = [
+        "under 30 days ago",
+        "30 to 90 days ago",
+        "90 to 365 days ago",
+        "over a year ago",
+    ];
+    for (label, count) in labels.iter().zip(summary.buckets) {
+        println!("    {:18}  {}", label, count);
+    }
+    if let Some(median) = summary.median_days {
+        println!("Median age: {} days", median);
+    }
+    if let Some(oldest) = summary.oldest {
+        println!(
+            "Oldest: {} ({}), last updated {}, {} days ago",
+            oldest.name,
+            oldest.version,
+            dates.format(oldest.seconds)?,
+            staleness::days_since(oldest.seconds, dates.now),
+        );
+    }
+
+    Ok(())
 }
 
-fn get_spec_lines(path: PathBuf) -> (HashSet<usize>, Vec<String>) {
-    let mut set = HashSet::new();
-    let mut lines = Vec::new();
-    let file = File::open(path).unwrap();
-    let reader = BufReader::new(file);
-    let re = Regex::new(r"\(\d+\.\d+\.\d+\.?\d?\)").unwrap();
+fn print_by_date(
+    attribution: &Attribution,
+    spec_lines: &BTreeMap<usize, String>,
+    authors: bool,
+    dates: &Dates,
+    granularity: Granularity,
+) -> Result<(), Error> {
+    // Each period keeps the time of its newest change, which orders them.
+    let mut map: BTreeMap<String, (i64, Vec<String>)> = BTreeMap::new();
+    let mut uncommitted = Vec::new();
 
-    for (i, line) in reader.lines().enumerate() {
-        if let Ok(line) = line {
-            if re.is_match(line.as_str()) {
-                set.insert(i);
+    for (&line, text) in spec_lines {
+        if let Some(hunk) = attribution.line(line) {
+            let text = if authors {
+                format!("{}  {}", text, who(&hunk.author, &hunk.committer))
+            } else {
+                text.clone()
+            };
+            if !hunk.is_committed() {
+                uncommitted.push(text);
+                continue;
             }
-            lines.push(line);
+            let label = dates.period(hunk.seconds, granularity)?;
+            let (newest, lines) = map.entry(label).or_insert((hunk.seconds, Vec::new()));
+            *newest = (*newest).max(hunk.seconds);
+            lines.push(text);
+        }
+    }
+
+    let mut periods = map.into_iter().collect::<Vec<_>>();
+    periods.sort_by_key(|(_, (newest, _))| *newest);
+    for (label, (newest, lines)) in periods {
+        println!("Updated {}:", dates.with_relative(label, newest));
+        for line in lines {
+            println!("{}", line);
+        }
+    }
+    if !uncommitted.is_empty() {
+        println!("Not committed yet:");
+        for line in uncommitted {
+            println!("{}", line);
         }
     }
 
-    (set, lines)
+    Ok(())
+}
+
+/// Lists one row per gem, followed by Bundler and Ruby.
+fn print_by_gem(
+    records: Vec<Record>,
+    runtimes: Vec<Record>,
+    sort: SortOrder,
+    authors: bool,
+    dates: &Dates,
+) -> Result<(), Error> {
+    let mut rows = report::latest_per_gem(records);
+    if let SortOrder::Age = sort {
+        rows.sort_by_key(|row| row.seconds);
+    }
+    rows.extend(runtimes.into_iter().map(|mut runtime| {
+        runtime.name = runtime_label(&runtime.name).to_string();
+        runtime
+    }));
+
+    let name_width = rows.iter().map(|row| row.name.len()).max().unwrap_or(0);
+    let version_width = rows.iter().map(|row| row.version.len()).max().unwrap_or(0);
+    for row in rows {
+        let mut line = format!(
+            "{:name_width$}  {:version_width$}  {}  {}",
+            row.name,
+            row.version,
+            dates.format(row.seconds)?,
+            &row.commit.to_string()[..7],
+        );
+        if authors {
+            line.push_str("  ");
+            line.push_str(&who(&row.author, &row.committer));
+        }
+        println!("{}", line);
+    }
+
+    Ok(())
+}
+
+/// Lists, for each author, the gems they were the last to change, with
+/// Bundler and Ruby under whoever last changed them.
+fn print_by_author(
+    records: Vec<Record>,
+    runtimes: Vec<Record>,
+    dates: &Dates,
+) -> Result<(), Error> {
+    let mut by_author: BTreeMap<Person, Vec<Record>> = BTreeMap::new();
+    for record in report::latest_per_gem(records).into_iter().chain(runtimes) {
+        by_author
+            .entry(record.author.clone())
+            .or_default()
+            .push(record);
+    }
+
+    for (author, records) in by_author {
+        println!("{} <{}>:", author.name, author.email);
+        for record in records {
+            println!(
+                "    {} {}",
+                describe(&record),
+                dates.format(record.seconds)?
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Lists the gems of each source in lockfile order, then Bundler and Ruby
+/// under their own sections. Git sources also show the revision they are
+/// pinned to and when that pin last moved.
+fn print_by_source(
+    records: Vec<Record>,
+    runtimes: Vec<Record>,
+    dates: &Dates,
+) -> Result<(), Error> {
+    let mut by_source: BTreeMap<usize, (Section, Source, Vec<Record>)> = BTreeMap::new();
+    for record in report::latest_per_gem(records) {
+        by_source
+            .entry(record.source.line)
+            .or_insert_with(|| (record.section.clone(), record.source.clone(), Vec::new()))
+            .2
+            .push(record);
+    }
+
+    for (section, source, records) in by_source.into_values() {
+        let mut header = format!("{} {}", section.as_str(), source.remotes.join(", "));
+        if let Some(branch) = &source.branch {
+            header.push_str(&format!(" (branch {})", branch));
+        }
+        if let Some(tag) = &source.tag {
+            header.push_str(&format!(" (tag {})", tag));
+        }
+        println!("{}:", header);
+
+        if let Some(revision) = &source.revision {
+            let short = &revision[..revision.len().min(7)];
+            match records.first().and_then(|record| record.revised) {
+                Some(revised) => {
+                    println!("    revision {} pinned {}", short, dates.format(revised)?)
+                }
+                None => println!("    revision {}", short),
+            }
+        }
+        for record in records {
+            println!(
+                "    {} {}",
+                describe(&record),
+                dates.format(record.seconds)?
+            );
+        }
+    }
+    for runtime in runtimes {
+        println!("{}:", runtime.section.as_str());
+        println!(
+            "    {} {}",
+            describe(&runtime),
+            dates.format(runtime.seconds)?
+        );
+    }
+
+    Ok(())
+}
+
+/// Names a gem as `rails (7.1.1)`, or Bundler and Ruby as `Bundler 2.4.10`
+/// the way the date report does.
+fn describe(record: &Record) -> String {
+    match record.section {
+        Section::BundledWith | Section::RubyVersion => {
+            format!("{} {}", runtime_label(&record.name), record.version)
+        }
+        _ => format!("{} ({})", record.name, record.version),
+    }
+}
+
+/// The author of a change, noting the committer when someone else applied it.
+fn who(author: &Person, committer: &Person) -> String {
+    if author.name == committer.name {
+        author.name.clone()
+    } else {
+        format!("{}, committed by {}", author.name, committer.name)
+    }
+}
+
+#[derive(Serialize)]
+struct JsonReport {
+    gems: Vec<JsonGem>,
+    bundler: Option<JsonRuntime>,
+    ruby: Option<JsonRuntime>,
+}
+
+#[derive(Serialize)]
+struct JsonRecursiveReport {
+    lockfiles: Vec<JsonLockfile>,
+}
+
+#[derive(Serialize)]
+struct JsonLockfile {
+    path: String,
+    gems: Vec<JsonGem>,
+    bundler: Option<JsonRuntime>,
+    ruby: Option<JsonRuntime>,
+}
+
+#[derive(Serialize)]
+struct JsonGem {
+    name: String,
+    version: String,
+    platform: Option<String>,
+    source: JsonSource,
+    /// When the line was last changed, in ISO 8601.
+    date: String,
+    commit: String,
+    author: String,
+    author_email: String,
+    committer: String,
+    committer_email: String,
+    /// One-based line number in the lockfile.
+    line: usize,
+}
+
+/// The version under `BUNDLED WITH` or `RUBY VERSION`.
+#[derive(Serialize)]
+struct JsonRuntime {
+    version: String,
+    /// When the line was last changed, in ISO 8601.
+    date: String,
+    commit: String,
+    author: String,
+    author_email: String,
+    /// One-based line number in the lockfile.
+    line: usize,
+}
+
+#[derive(Serialize)]
+struct JsonSource {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    /// The gem servers, git URL or path the source points at.
+    remotes: Vec<String>,
+    revision: Option<String>,
+    /// When the revision last changed, in ISO 8601.
+    revision_date: Option<String>,
+    branch: Option<String>,
+    tag: Option<String>,
+}
+
+/// Prints one lockfile as `{"gems": [...]}`, or several as
+/// `{"lockfiles": [{"path": ..., "gems": [...]}]}`.
+fn print_json(analyses: &[Analysis], recursive: bool) -> Result<(), Error> {
+    let json = if recursive {
+        let lockfiles = analyses
+            .iter()
+            .map(|analysis| {
+                Ok(JsonLockfile {
+                    path: analysis.path.display().to_string(),
+                    gems: json_gems(analysis.records())?,
+                    bundler: json_runtime(analysis, "bundler")?,
+                    ruby: json_runtime(analysis, "ruby")?,
+                })
+            })
+            .collect::<Result<_, Error>>()?;
+        serde_json::to_string_pretty(&JsonRecursiveReport { lockfiles })?
+    } else {
+        let analysis = &analyses[0];
+        serde_json::to_string_pretty(&JsonReport {
+            gems: json_gems(analysis.records())?,
+            bundler: json_runtime(analysis, "bundler")?,
+            ruby: json_runtime(analysis, "ruby")?,
+        })?
+    };
+
+    println!("{}", json);
+    Ok(())
+}
+
+fn json_runtime(analysis: &Analysis, name: &str) -> Result<Option<JsonRuntime>, Error> {
+    let record = match analysis
+        .runtimes()
+        .into_iter()
+        .find(|record| record.name == name)
+    {
+        Some(record) => record,
+        None => return Ok(None),
+    };
+
+    Ok(Some(JsonRuntime {
+        version: record.version,
+        date: iso8601(record.seconds)?,
+        commit: record.commit.to_string(),
+        author: record.author.name,
+        author_email: record.author.email,
+        line: record.line + 1,
+    }))
+}
+
+fn iso8601(seconds: i64) -> Result<String, Error> {
+    Utc.timestamp_opt(seconds, 0)
+        .single()
+        .map(|date| date.to_rfc3339_opts(SecondsFormat::Secs, true))
+        .ok_or(Error::InvalidTimestamp(seconds))
+}
+
+fn json_gems(records: Vec<Record>) -> Result<Vec<JsonGem>, Error> {
+    records
+        .into_iter()
+        .map(|record| {
+            Ok(JsonGem {
+                name: record.name,
+                version: record.version,
+                platform: record.platform,
+                source: JsonSource {
+                    kind: match record.section {
+                        Section::Git => "git",
+                        Section::Path => "path",
+                        Section::Plugin => "plugin",
+                        _ => "gem",
+                    },
+                    remotes: record.source.remotes,
+                    revision: record.source.revision,
+                    revision_date: record.revised.map(iso8601).transpose()?,
+                    branch: record.source.branch,
+                    tag: record.source.tag,
+                },
+                date: iso8601(record.seconds)?,
+                commit: record.commit.to_string(),
+                author: record.author.name,
+                author_email: record.author.email,
+                committer: record.committer.name,
+                committer_email: record.committer.email,
+                line: record.line + 1,
+            })
+        })
+        .collect()
+}
+
+/// How the Bundler and Ruby versions are named in text output.
+fn runtime_label(name: &str) -> &str {
+    match name {
+        "bundler" => "Bundler",
+        "ruby" => "Ruby",
+        other => other,
+    }
+}
+
+fn read_lockfile(path: &Path) -> Result<String, Error> {
+    fs::read_to_string(path).map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => Error::LockfileNotFound(path.to_path_buf()),
+        _ => Error::LockfileUnreadable(path.to_path_buf(), e),
+    })
+}
+
+/// Returns the lines worth reporting, keyed by their zero-based line number.
+///
+/// Resolved specs are included along with the Bundler and Ruby versions.
+/// Dependency constraint lines are only included if `constraints` is set, and
+/// are labelled with the gem that declares them.
+fn get_spec_lines(
+    contents: &str,
+    entries: &[lockfile::Entry],
+    constraints: bool,
+) -> BTreeMap<usize, String> {
+    let lines = contents.lines().collect::<Vec<_>>();
+
+    entries
+        .iter()
+        .filter_map(|entry| {
+            let line = lines[entry.line];
+            match &entry.kind {
+                lockfile::Kind::Spec => Some((entry.line, line.to_string())),
+                lockfile::Kind::Constraint { parent } if constraints => {
+                    Some((entry.line, format!("{} (required by {})", line, parent)))
+                }
+                lockfile::Kind::Constraint { .. } | lockfile::Kind::Platform => None,
+                lockfile::Kind::Runtime => Some((
+                    entry.line,
+                    format!(
+                        "    {} {}",
+                        runtime_label(&entry.name),
+                        entry.version.as_deref().unwrap_or_default()
+                    ),
+                )),
+            }
+        })
+        .collect()
 }
@@ -0,0 +1,133 @@
+use crate::error::Error;
+use git2::{Commit, ObjectType, Repository, TreeWalkMode, TreeWalkResult};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A lockfile and the git repository that tracks it.
+pub struct Project {
+    pub repo: Repository,
+    /// Absolute path to the root of the working tree.
+    pub workdir: PathBuf,
+    /// Path to the lockfile relative to the root of the working tree, as git
+    /// sees it.
+    pub relative: PathBuf,
+}
+
+impl Project {
+    /// Finds the repository containing `directory`, searching upwards.
+    ///
+    /// `directory` may be anywhere inside the working tree, including a
+    /// linked worktree or a submodule whose `.git` is a file.
+    pub fn discover(directory: &Path, name: &str) -> Result<Project, Error> {
+        let directory = fs::canonicalize(directory)
+            .map_err(|_| Error::LockfileNotFound(directory.join(name)))?;
+        let repo = Repository::discover(&directory)
+            .map_err(|e| Error::NotARepository(directory.clone(), e))?;
+        let workdir = match repo.workdir() {
+            Some(workdir) => fs::canonicalize(workdir)
+                .map_err(|e| Error::LockfileUnreadable(workdir.to_path_buf(), e))?,
+            None => {
+                return Err(Error::NotARepository(
+                    directory,
+                    git2::Error::from_str("a bare repository has no working tree"),
+                ))
+            }
+        };
+
+        let lockfile = directory.join(name);
+        let relative = lockfile
+            .strip_prefix(&workdir)
+            .map(Path::to_path_buf)
+            .map_err(|_| Error::Untracked(lockfile.clone()))?;
+
+        Ok(Project {
+            repo,
+            workdir,
+            relative,
+        })
+    }
+
+    /// Absolute path to the lockfile on disk.
+    pub fn lockfile(&self) -> PathBuf {
+        self.workdir.join(&self.relative)
+    }
+
+    /// Every lockfile at or below the lockfile's directory in the tree of
+    /// `rev`, or without one, committed in HEAD and still in the working
+    /// tree.
+    ///
+    /// Lockfiles that are only staged, or deleted from the working tree, are
+    /// left out rather than failing the whole scan.
+    pub fn tracked_lockfiles(&self, rev: Option<&Commit>) -> Result<Vec<PathBuf>, Error> {
+        let paths = match rev {
+            Some(commit) => tree_paths(commit)?,
+            None => {
+                // A repository without commits can't have tracked a lockfile yet.
+                let head = self
+                    .repo
+                    .head()
+                    .and_then(|head| head.peel_to_commit())
+                    .map_err(|_| Error::Untracked(self.lockfile()))?;
+                tree_paths(&head)?
+                    .into_iter()
+                    .filter(|path| self.workdir.join(path).is_file())
+                    .collect()
+            }
+        };
+
+        let base = self.relative.parent().unwrap_or(Path::new(""));
+        let mut lockfiles = paths
+            .into_iter()
+            .filter(|path| path.starts_with(base) && is_lockfile(path))
+            .collect::<Vec<_>>();
+        lockfiles.sort();
+
+        Ok(lockfiles)
+    }
+
+    /// Reads the lockfile at `relative` from the tree of `commit`.
+    pub fn read_at(&self, commit: &Commit, relative: &Path) -> Result<String, Error> {
+        let not_found = || Error::NotAtRevision(relative.to_path_buf(), commit.id().to_string());
+        let entry = commit.tree()?.get_path(relative).map_err(|_| not_found())?;
+        let blob = entry
+            .to_object(&self.repo)?
+            .into_blob()
+            .map_err(|_| not_found())?;
+
+        Ok(String::from_utf8_lossy(blob.content()).into_owned())
+    }
+
+    /// Resolves a revision such as `main`, `v1.4.0` or `HEAD~3` to a commit.
+    pub fn resolve(&self, rev: &str) -> Result<Commit<'_>, Error> {
+        self.repo
+            .revparse_single(rev)
+            .and_then(|object| object.peel_to_commit())
+            .map_err(|e| Error::UnknownRevision(rev.to_string(), e))
+    }
+}
+
+/// The path of every file in the tree of `commit`.
+fn tree_paths(commit: &Commit) -> Result<Vec<PathBuf>, git2::Error> {
+    let mut paths = Vec::new();
+    commit.tree()?.walk(TreeWalkMode::PreOrder, |root, entry| {
+        if entry.kind() == Some(ObjectType::Blob) {
+            if let Some(name) = entry.name() {
+                paths.push(Path::new(root).join(name));
+            }
+        }
+        TreeWalkResult::Ok
+    })?;
+
+    Ok(paths)
+}
+
+/// Whether `path` names a Bundler lockfile, such as `Gemfile.lock`,
+/// `Gemfile_next.lock` or `gems.locked`.
+fn is_lockfile(path: &Path) -> bool {
+    match path.file_name().and_then(|name| name.to_str()) {
+        Some(name) => {
+            name == "gems.locked" || (name.starts_with("Gemfile") && name.ends_with(".lock"))
+        }
+        None => false,
+    }
+}
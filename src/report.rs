@@ -0,0 +1,137 @@
+use crate::blame::{Attribution, Person};
+use crate::lockfile::{Entry, Kind, Section, Source};
+use crate::version;
+use git2::Oid;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// A lockfile read from disk together with its blame.
+pub struct Analysis {
+    /// Path to the lockfile relative to the root of the working tree.
+    pub path: PathBuf,
+    pub contents: String,
+    pub entries: Vec<Entry>,
+    pub attribution: Attribution,
+}
+
+impl Analysis {
+    pub fn records(&self) -> Vec<Record> {
+        records(&self.attribution, &self.entries)
+    }
+
+    /// The Bundler and Ruby versions the lockfile was resolved with, dated
+    /// like gems.
+    pub fn runtimes(&self) -> Vec<Record> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.kind == Kind::Runtime)
+            .filter_map(|entry| record(&self.attribution, entry))
+            .collect()
+    }
+}
+
+/// A resolved gem together with the blame of its line in the lockfile.
+pub struct Record {
+    pub name: String,
+    pub version: String,
+    pub platform: Option<String>,
+    pub section: Section,
+    pub source: Source,
+    /// When the revision a `GIT` source is pinned to last changed.
+    pub revised: Option<i64>,
+    /// Zero-based line number in the lockfile.
+    pub line: usize,
+    pub seconds: i64,
+    pub commit: Oid,
+    pub author: Person,
+    pub committer: Person,
+}
+
+/// Attributes every resolved spec in `entries` to the hunk that last touched it.
+pub fn records(attribution: &Attribution, entries: &[Entry]) -> Vec<Record> {
+    entries
+        .iter()
+        .filter(|entry| entry.kind == Kind::Spec)
+        .filter_map(|entry| record(attribution, entry))
+        .collect()
+}
+
+fn record(attribution: &Attribution, entry: &Entry) -> Option<Record> {
+    let hunk = attribution.line(entry.line)?;
+    Some(Record {
+        name: entry.name.clone(),
+        version: entry.version.clone().unwrap_or_default(),
+        platform: entry.platform.clone(),
+        section: entry.section.clone(),
+        source: entry.source.clone(),
+        revised: entry
+            .source
+            .revision_line
+            .and_then(|line| attribution.line(line))
+            .map(|hunk| hunk.seconds),
+        line: entry.line,
+        seconds: hunk.seconds,
+        commit: hunk.commit,
+        author: hunk.author.clone(),
+        committer: hunk.committer.clone(),
+    })
+}
+
+/// Collapses platform variants into one record per gem, ordered by name.
+///
+/// Where variants were touched at different times the latest one wins.
+pub fn latest_per_gem(records: Vec<Record>) -> Vec<Record> {
+    let mut latest: BTreeMap<String, Record> = BTreeMap::new();
+    for record in records {
+        match latest.get(&record.name) {
+            Some(existing) if existing.seconds >= record.seconds => {}
+            _ => {
+                latest.insert(record.name.clone(), record);
+            }
+        }
+    }
+
+    latest.into_values().collect()
+}
+
+/// Gems locked by more than one lockfile at different versions.
+///
+/// Each gem maps to the lockfiles that lock it, oldest version first, so the
+/// first lockfile is the one furthest behind.
+pub fn shared_gems(analyses: &[Analysis]) -> BTreeMap<String, Vec<(&Path, Record)>> {
+    let mut shared: BTreeMap<String, Vec<(&Path, Record)>> = BTreeMap::new();
+    for analysis in analyses {
+        for record in latest_per_gem(analysis.records()) {
+            shared
+                .entry(record.name.clone())
+                .or_default()
+                .push((&analysis.path, record));
+        }
+    }
+
+    shared.retain(|_, locked| {
+        locked
+            .iter()
+            .any(|(_, record)| record.version != locked[0].1.version)
+    });
+    for locked in shared.values_mut() {
+        locked.sort_by(|(_, a), (_, b)| version::compare(&a.version, &b.version));
+    }
+
+    shared
+}
+
+/// The platform variants of every gem, as `(platform, version)` pairs in
+/// lockfile order, keyed by gem name. Variants without a platform suffix are
+/// the `ruby` platform.
+pub fn platform_variants(entries: &[Entry]) -> BTreeMap<String, Vec<(String, String)>> {
+    let mut variants: BTreeMap<String, Vec<(String, String)>> = BTreeMap::new();
+    for entry in entries.iter().filter(|entry| entry.kind == Kind::Spec) {
+        variants.entry(entry.name.clone()).or_default().push((
+            entry.platform.clone().unwrap_or_else(|| "ruby".to_string()),
+            entry.version.clone().unwrap_or_default(),
+        ));
+    }
+
+    variants
+}
@@ -0,0 +1,137 @@
+use crate::report::Record;
+use std::fmt;
+use std::str::FromStr;
+
+pub const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// A gem age threshold such as `90d`, `12w` or `1y`.
+///
+/// A bare number is read as a number of days.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Age {
+    pub days: i64,
+    unit: char,
+    amount: i64,
+}
+
+impl Age {
+    fn seconds(&self) -> i64 {
+        self.days * SECONDS_PER_DAY
+    }
+}
+
+impl FromStr for Age {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Age, String> {
+        let s = s.trim();
+        let (amount, unit) = match s.char_indices().last() {
+            Some((i, unit)) if unit.is_ascii_alphabetic() => (&s[..i], unit),
+            _ => (s, 'd'),
+        };
+        let amount = amount
+            .parse::<i64>()
+            .map_err(|_| format!("`{}` is not an age like 90d, 12w or 1y", s))?;
+        // Every gem is at least zero days old, so such a threshold would
+        // flag them all.
+        if amount <= 0 {
+            return Err(format!("`{}` is not a positive age", s));
+        }
+        let days = match unit {
+            'd' => Some(amount),
+            'w' => amount.checked_mul(7),
+            'y' => amount.checked_mul(365),
+            _ => return Err(format!("unknown age unit `{}`, expected d, w or y", unit)),
+        };
+        // `seconds` must not overflow either when comparing against it.
+        let days = days
+            .filter(|days| days.checked_mul(SECONDS_PER_DAY).is_some())
+            .ok_or_else(|| format!("`{}` is too long an age", s))?;
+
+        Ok(Age { days, unit, amount })
+    }
+}
+
+impl fmt::Display for Age {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.amount, self.unit)
+    }
+}
+
+/// The gems that crossed each threshold.
+#[derive(Default)]
+pub struct Staleness<'a> {
+    /// Gems older than `--max-age`.
+    pub failed: Vec<&'a Record>,
+    /// Gems older than `--warn-age` but within `--max-age`.
+    pub warned: Vec<&'a Record>,
+}
+
+/// Sorts gems into those past the max age and those past the warn age.
+pub fn check<'a>(
+    records: &'a [Record],
+    now: i64,
+    max_age: Option<Age>,
+    warn_age: Option<Age>,
+) -> Staleness<'a> {
+    let mut staleness = Staleness::default();
+
+    for record in records {
+        let age = now - record.seconds;
+        if max_age.is_some_and(|max| age > max.seconds()) {
+            staleness.failed.push(record);
+        } else if warn_age.is_some_and(|warn| age > warn.seconds()) {
+            staleness.warned.push(record);
+        }
+    }
+
+    staleness
+}
+
+/// Whole days between `seconds` and `now`.
+pub fn days_since(seconds: i64, now: i64) -> i64 {
+    (now - seconds) / SECONDS_PER_DAY
+}
+
+/// Upper bounds, in days, of every age bucket but the last.
+const BUCKETS: [i64; 3] = [30, 90, 365];
+
+/// How the age of the gems in a lockfile is spread out.
+pub struct Summary<'a> {
+    /// Gem counts updated in under 30 days, 30 to 90 days, 90 to 365 days and
+    /// over a year ago.
+    pub buckets: [usize; 4],
+    /// The median age in whole days, if there are any gems.
+    pub median_days: Option<i64>,
+    /// The gem that has gone longest without an update.
+    pub oldest: Option<&'a Record>,
+}
+
+/// Buckets gems by how long ago they were last updated.
+pub fn summarize(records: &[Record], now: i64) -> Summary<'_> {
+    let mut buckets = [0; 4];
+    let mut ages = Vec::with_capacity(records.len());
+
+    for record in records {
+        let days = days_since(record.seconds, now);
+        let bucket = BUCKETS
+            .iter()
+            .position(|&bound| days < bound)
+            .unwrap_or(BUCKETS.len());
+        buckets[bucket] += 1;
+        ages.push(days);
+    }
+    ages.sort_unstable();
+
+    let median_days = match ages.len() {
+        0 => None,
+        n if n % 2 == 1 => Some(ages[n / 2]),
+        n => Some((ages[n / 2 - 1] + ages[n / 2]) / 2),
+    };
+
+    Summary {
+        buckets,
+        median_days,
+        oldest: records.iter().min_by_key(|record| record.seconds),
+    }
+}
@@ -0,0 +1,308 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+/// How far apart two versions are, by the first segment that differs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Level {
+    Major,
+    Minor,
+    Patch,
+}
+
+impl Level {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Level::Major => "major",
+            Level::Minor => "minor",
+            Level::Patch => "patch",
+        }
+    }
+}
+
+/// A part of a version: a run of digits or a run of letters.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Segment {
+    Number(u64),
+    Letters(String),
+}
+
+impl Segment {
+    const ZERO: Segment = Segment::Number(0);
+}
+
+/// A gem version, ordered the way RubyGems' `Gem::Version` orders them.
+///
+/// `1.0.0.rc1` splits into the segments `1`, `0`, `0`, `rc` and `1`. Letters
+/// mark a prerelease, which sorts before the release it leads up to, and
+/// trailing zeros don't matter, so `1.0` equals `1.0.0`.
+#[derive(Clone, Debug)]
+pub struct Version {
+    original: String,
+    segments: Vec<Segment>,
+}
+
+impl Version {
+    /// Whether the version contains letters, like `7.1.0.rc1` or `2.0.0.pre`.
+    pub fn is_prerelease(&self) -> bool {
+        self.segments
+            .iter()
+            .any(|segment| matches!(segment, Segment::Letters(_)))
+    }
+
+    /// The segments before the first letters, so `7.1.0.rc1` gives `7.1.0`.
+    fn release(&self) -> Version {
+        let segments = self
+            .segments
+            .iter()
+            .take_while(|segment| matches!(segment, Segment::Number(_)))
+            .cloned()
+            .collect::<Vec<_>>();
+        Version::from_segments(segments)
+    }
+
+    /// The upper bound of `~>`: drops any prerelease and the last segment,
+    /// then increments the new last one, so `2.2.8` bumps to `2.3` and `2`
+    /// to `3`.
+    fn bump(&self) -> Version {
+        let mut segments = self.release().segments;
+        if segments.len() > 1 {
+            segments.pop();
+        }
+        if let Some(Segment::Number(last)) = segments.last_mut() {
+            *last += 1;
+        }
+        Version::from_segments(segments)
+    }
+
+    fn from_segments(segments: Vec<Segment>) -> Version {
+        let original = segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Number(n) => n.to_string(),
+                Segment::Letters(letters) => letters.clone(),
+            })
+            .collect::<Vec<_>>()
+            .join(".");
+        Version { original, segments }
+    }
+
+    /// The segments with trailing zeros removed from both the release and
+    /// the prerelease part, so `1.0.a.0` compares like `1.a`.
+    fn canonical(&self) -> Vec<&Segment> {
+        let split = self
+            .segments
+            .iter()
+            .position(|segment| matches!(segment, Segment::Letters(_)))
+            .unwrap_or(self.segments.len());
+        let (release, prerelease) = self.segments.split_at(split);
+
+        trim_zeros(release)
+            .iter()
+            .chain(trim_zeros(prerelease))
+            .collect()
+    }
+}
+
+fn trim_zeros(segments: &[Segment]) -> &[Segment] {
+    let end = segments
+        .iter()
+        .rposition(|segment| *segment != Segment::ZERO)
+        .map_or(0, |i| i + 1);
+    &segments[..end]
+}
+
+impl FromStr for Version {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Version, String> {
+        let invalid = || format!("`{}` is not a valid gem version", s);
+        let trimmed = s.trim();
+        if !trimmed.starts_with(|c: char| c.is_ascii_digit())
+            || !trimmed
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '.')
+            || trimmed.ends_with('.')
+            || trimmed.contains("..")
+        {
+            return Err(invalid());
+        }
+
+        let mut segments = Vec::new();
+        let mut rest = trimmed;
+        while let Some(c) = rest.chars().next() {
+            if c == '.' {
+                rest = &rest[1..];
+                continue;
+            }
+            let end = if c.is_ascii_digit() {
+                rest.find(|c: char| !c.is_ascii_digit())
+            } else {
+                rest.find(|c: char| !c.is_ascii_alphabetic())
+            }
+            .unwrap_or(rest.len());
+            let run = &rest[..end];
+            segments.push(if c.is_ascii_digit() {
+                Segment::Number(run.parse().map_err(|_| invalid())?)
+            } else {
+                Segment::Letters(run.to_string())
+            });
+            rest = &rest[end..];
+        }
+
+        Ok(Version {
+            original: trimmed.to_string(),
+            segments,
+        })
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.original)
+    }
+}
+
+impl Ord for Version {
+    /// Compares canonical segments pairwise, padding the shorter version
+    /// with zeros. Letters sort before numbers, which is what puts `1.0.a`
+    /// before `1.0`.
+    fn cmp(&self, other: &Version) -> Ordering {
+        let left = self.canonical();
+        let right = other.canonical();
+
+        for i in 0..left.len().max(right.len()) {
+            let a = left.get(i).copied().unwrap_or(&Segment::ZERO);
+            let b = right.get(i).copied().unwrap_or(&Segment::ZERO);
+            let ordering = match (a, b) {
+                (Segment::Number(a), Segment::Number(b)) => a.cmp(b),
+                (Segment::Letters(a), Segment::Letters(b)) => a.cmp(b),
+                (Segment::Letters(_), Segment::Number(_)) => Ordering::Less,
+                (Segment::Number(_), Segment::Letters(_)) => Ordering::Greater,
+            };
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+        }
+
+        Ordering::Equal
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Version) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Version) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Version {}
+
+/// The comparison in a single requirement constraint.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Operator {
+    Equal,
+    NotEqual,
+    Greater,
+    Less,
+    GreaterOrEqual,
+    LessOrEqual,
+    /// `~>`, allowing later versions up to the next bump of the target.
+    Pessimistic,
+}
+
+/// A set of version constraints, matched the way RubyGems'
+/// `Gem::Requirement` matches them, e.g. `~> 5.2.6, >= 5.2.6.2`.
+#[derive(Clone, Debug)]
+pub struct Requirement {
+    constraints: Vec<(Operator, Version)>,
+}
+
+impl Requirement {
+    /// Whether `version` meets every constraint.
+    pub fn satisfied_by(&self, version: &Version) -> bool {
+        self.constraints
+            .iter()
+            .all(|(operator, target)| match operator {
+                Operator::Equal => version == target,
+                Operator::NotEqual => version != target,
+                Operator::Greater => version > target,
+                Operator::Less => version < target,
+                Operator::GreaterOrEqual => version >= target,
+                Operator::LessOrEqual => version <= target,
+                Operator::Pessimistic => version >= target && version.release() < target.bump(),
+            })
+    }
+}
+
+impl FromStr for Requirement {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Requirement, String> {
+        let constraints = s
+            .split(',')
+            .map(|constraint| {
+                let constraint = constraint.trim();
+                let start = constraint
+                    .find(|c: char| c.is_ascii_digit())
+                    .ok_or_else(|| format!("`{}` is not a valid requirement", constraint))?;
+                let operator = match constraint[..start].trim() {
+                    "" | "=" => Operator::Equal,
+                    "!=" => Operator::NotEqual,
+                    ">" => Operator::Greater,
+                    "<" => Operator::Less,
+                    ">=" => Operator::GreaterOrEqual,
+                    "<=" => Operator::LessOrEqual,
+                    "~>" => Operator::Pessimistic,
+                    other => return Err(format!("unknown requirement operator `{}`", other)),
+                };
+                Ok((operator, constraint[start..].parse()?))
+            })
+            .collect::<Result<_, String>>()?;
+
+        Ok(Requirement { constraints })
+    }
+}
+
+/// Orders two version strings, falling back to comparing them as text when
+/// either is not a valid version.
+pub fn compare(a: &str, b: &str) -> Ordering {
+    match (a.parse::<Version>(), b.parse::<Version>()) {
+        (Ok(a), Ok(b)) => a.cmp(&b),
+        _ => a.cmp(b),
+    }
+}
+
+/// Whether the version string is a prerelease such as `7.1.0.rc1`.
+pub fn is_prerelease(version: &str) -> bool {
+    version
+        .parse::<Version>()
+        .is_ok_and(|version| version.is_prerelease())
+}
+
+/// Classifies the change from `a` to `b` as a major, minor or patch bump.
+///
+/// Anything past the second release segment, including prerelease tags,
+/// counts as a patch. Returns `None` if the versions are equal or either is
+/// invalid.
+pub fn level(a: &str, b: &str) -> Option<Level> {
+    let a = a.parse::<Version>().ok()?;
+    let b = b.parse::<Version>().ok()?;
+    if a == b {
+        return None;
+    }
+    let (a, b) = (a.release().segments, b.release().segments);
+    let differs = (0..a.len().max(b.len()))
+        .find(|&i| a.get(i).unwrap_or(&Segment::ZERO) != b.get(i).unwrap_or(&Segment::ZERO));
+
+    Some(match differs {
+        Some(0) => Level::Major,
+        Some(1) => Level::Minor,
+        _ => Level::Patch,
+    })
+}
@@ -0,0 +1,541 @@
+mod common;
+
+use chrono::{Duration, Utc};
+use common::{stdout, Fixture, LOCKFILE};
+
+fn days_ago(days: i64) -> String {
+    (Utc::now() - Duration::days(days))
+        .format("%Y-%m-%d")
+        .to_string()
+}
+
+#[test]
+fn diff_classifies_changes_between_revisions() {
+    let fixture = Fixture::new();
+    fixture.commit("Gemfile.lock", LOCKFILE, "Alice", "2023-01-05");
+    let next = LOCKFILE
+        .replace("rails (7.0.4)", "rails (7.1.1)")
+        .replace("rack (2.2.8)", "rack (2.2.7)")
+        .replace("    racc (1.7.1)\n", "    puma (6.4.0)\n");
+    fixture.commit("Gemfile.lock", &next, "Bob", "2023-06-05");
+
+    let output = stdout(&fixture.depr(&["diff", "HEAD~1..HEAD"]));
+
+    assert_eq!(
+        output,
+        "\
+Added:
+    puma 6.4.0
+Removed:
+    racc 1.7.1
+Upgraded:
+    rails 7.0.4 -> 7.1.1 (minor)
+Downgraded:
+    rack 2.2.8 -> 2.2.7 (patch)
+"
+    );
+}
+
+#[test]
+fn diff_with_three_dots_compares_against_the_merge_base() {
+    let fixture = Fixture::new();
+    let base = fixture.commit("Gemfile.lock", LOCKFILE, "Alice", "2023-01-05");
+    let rails = LOCKFILE.replace("rails (7.0.4)", "rails (7.1.1)");
+    let branch = fixture.branch(base, "Gemfile.lock", &rails, "Bob", "2023-02-01");
+    let rack = LOCKFILE.replace("rack (2.2.8)", "rack (2.2.9)");
+    fixture.commit("Gemfile.lock", &rack, "Carol", "2023-03-01");
+
+    let two_dots = stdout(&fixture.depr(&["diff", &format!("HEAD..{}", branch)]));
+    let three_dots = stdout(&fixture.depr(&["diff", &format!("HEAD...{}", branch)]));
+
+    assert!(two_dots.contains("    rack 2.2.9 -> 2.2.8 (patch)\n"));
+    assert_eq!(three_dots, "Upgraded:\n    rails 7.0.4 -> 7.1.1 (minor)\n");
+}
+
+#[test]
+fn diff_orders_versions_like_rubygems() {
+    let fixture = Fixture::new();
+    let old = LOCKFILE.replace("rails (7.0.4)", "rails (7.1.0.rc1)");
+    fixture.commit("Gemfile.lock", &old, "Alice", "2023-01-05");
+    let new = LOCKFILE
+        .replace("rails (7.0.4)", "rails (7.1.0)")
+        .replace("racc (1.7.1)", "racc (1.7.1.0)")
+        .replace("rack (2.2.8)", "rack (2.2.10)")
+        .replace("actionpack (7.0.4)", "actionpack (7.0.4.beta1)");
+    fixture.commit("Gemfile.lock", &new, "Bob", "2023-06-05");
+
+    let output = stdout(&fixture.depr(&["diff", "HEAD~1..HEAD"]));
+
+    assert_eq!(
+        output,
+        "\
+Upgraded:
+    rack 2.2.8 -> 2.2.10 (patch)
+    rails 7.1.0.rc1 -> 7.1.0 (patch)
+Downgraded:
+    actionpack 7.0.4 -> 7.0.4.beta1 (patch)
+"
+    );
+}
+
+#[test]
+fn diff_compares_platform_variants_on_their_own() {
+    let fixture = Fixture::new();
+    fixture.commit("Gemfile.lock", LOCKFILE, "Alice", "2023-01-05");
+    let next = LOCKFILE
+        .replace(
+            "    racc (1.7.1)\n",
+            "    nokogiri (1.15.5-arm64-darwin)\n      racc (~> 1.4)\n    racc (1.7.1)\n",
+        )
+        .replace("  x86_64-linux\n", "  arm64-darwin\n  x86_64-linux\n");
+    fixture.commit("Gemfile.lock", &next, "Bob", "2023-06-05");
+    let upgraded = next.replace(
+        "nokogiri (1.15.4-x86_64-linux)",
+        "nokogiri (1.15.5-x86_64-linux)",
+    );
+    fixture.commit("Gemfile.lock", &upgraded, "Bob", "2023-07-05");
+
+    let added = stdout(&fixture.depr(&["diff", "HEAD~2..HEAD~1"]));
+    let upgraded = stdout(&fixture.depr(&["diff", "HEAD~1..HEAD"]));
+
+    assert_eq!(added, "Added:\n    nokogiri 1.15.5 on arm64-darwin\n");
+    assert_eq!(
+        upgraded,
+        "Upgraded:\n    nokogiri 1.15.4 -> 1.15.5 on x86_64-linux (patch)\n"
+    );
+}
+
+#[test]
+fn summary_buckets_gems_by_age() {
+    let fixture = Fixture::new();
+    fixture.commit("Gemfile.lock", LOCKFILE, "Alice", &days_ago(400));
+    let rack = LOCKFILE.replace("rack (2.2.8)", "rack (2.2.9)");
+    fixture.commit("Gemfile.lock", &rack, "Bob", &days_ago(60));
+    let rails = rack.replace("rails (7.0.4)", "rails (7.1.1)");
+    fixture.commit("Gemfile.lock", &rails, "Bob", &days_ago(10));
+
+    let output = stdout(&fixture.depr(&["summary"]));
+    let lines = output.lines().collect::<Vec<_>>();
+
+    assert_eq!(
+        lines[..5],
+        [
+            "5 gems last updated:",
+            "    under 30 days ago   1",
+            "    30 to 90 days ago   1",
+            "    90 to 365 days ago  0",
+            "    over a year ago     3",
+        ]
+    );
+    assert!(lines[6].starts_with("Oldest: actionpack (7.0.4), last updated "));
+}
+
+#[test]
+fn libyear_compares_locked_and_newest_release_dates() {
+    let fixture = Fixture::new();
+    fixture.commit("Gemfile.lock", LOCKFILE, "Alice", "2023-01-05");
+    let releases = fixture.path().join("releases");
+    std::fs::create_dir(&releases).unwrap();
+    std::fs::write(
+        releases.join("rails.json"),
+        r#"[
+            {"number": "7.2.0.beta1", "prerelease": true, "created_at": "2024-06-01T00:00:00.000Z"},
+            {"number": "7.1.1", "prerelease": false, "created_at": "2023-10-10T00:00:00.000Z"},
+            {"number": "7.0.4", "prerelease": false, "created_at": "2022-09-09T00:00:00.000Z"}
+        ]"#,
+    )
+    .unwrap();
+    std::fs::write(
+        releases.join("rack.json"),
+        r#"[{"number": "2.2.8", "prerelease": false, "created_at": "2023-07-31T00:00:00.000Z"}]"#,
+    )
+    .unwrap();
+    std::fs::write(
+        releases.join("racc.json"),
+        r#"[{"number": "1.7.3", "prerelease": false, "created_at": "2023-10-28T00:00:00.000Z"}]"#,
+    )
+    .unwrap();
+
+    let output = stdout(&fixture.depr(&["libyear", "--releases", "releases"]));
+
+    assert_eq!(
+        output,
+        "\
+actionpack  7.0.4   not in index
+nokogiri    1.15.4  not in index
+racc        1.7.1   locked version not in releases
+rack        2.2.8   2.2.8  0.00
+rails       7.0.4   7.1.1  1.08
+Total: 1.08 libyears
+"
+    );
+}
+
+#[test]
+fn outdated_reads_the_compact_index() {
+    let fixture = Fixture::new();
+    fixture.commit("Gemfile.lock", LOCKFILE, "Alice", "2023-01-05");
+    let index = fixture.path().join("index");
+    std::fs::create_dir_all(index.join("info")).unwrap();
+    std::fs::write(
+        index.join("versions"),
+        "\
+created_at: 2024-01-01T00:00:00Z
+---
+actionpack 7.0.4,7.0.8 a1
+rack 2.2.8,3.0.0 b2
+rails 7.0.4,7.1.1,7.2.0.beta1 c3
+rack -3.0.0 d4
+",
+    )
+    .unwrap();
+    std::fs::write(
+        index.join("info/nokogiri"),
+        "---\n1.15.4-x86_64-linux racc:~> 1.4|checksum:e5\n2.0.0 |checksum:f6\n",
+    )
+    .unwrap();
+
+    let output = stdout(&fixture.depr(&["outdated", "--index", "index"]));
+
+    assert_eq!(
+        output,
+        "\
+actionpack  7.0.4   7.0.8  patch       last updated 2023-01-05
+nokogiri    1.15.4  2.0.0  major       last updated 2023-01-05
+racc        1.7.1          unknown     last updated 2023-01-05
+rack        2.2.8   2.2.8  up to date  last updated 2023-01-05
+rails       7.0.4   7.1.1  minor       last updated 2023-01-05
+"
+    );
+}
+
+#[test]
+fn audit_reports_advisories_with_how_long_they_were_locked() {
+    let fixture = Fixture::new();
+    fixture.commit("Gemfile.lock", LOCKFILE, "Alice", "2023-01-05");
+    let bumped = LOCKFILE.replace("rails (7.0.4)", "rails (7.0.8)");
+    fixture.commit("Gemfile.lock", &bumped, "Bob", "2023-09-05");
+    let db = fixture.path().join("ruby-advisory-db");
+    std::fs::create_dir_all(db.join("gems/rack")).unwrap();
+    std::fs::create_dir_all(db.join("gems/rails")).unwrap();
+    std::fs::write(
+        db.join("gems/rack/CVE-2024-26146.yml"),
+        "\
+---
+gem: rack
+cve: 2024-26146
+ghsa: 54rr-7fvw-6x8f
+url: https://github.com/advisories/GHSA-54rr-7fvw-6x8f
+title: Possible Denial of Service Vulnerability in Rack Header Parsing
+date: 2024-02-21
+patched_versions:
+  - \"~> 2.0.9, >= 2.0.9.4\"
+  - \"~> 2.2.8, >= 2.2.8.1\"
+  - \">= 3.0.9.1\"
+",
+    )
+    .unwrap();
+    std::fs::write(
+        db.join("gems/rails/GHSA-0000-1111-2222.yml"),
+        "\
+---
+gem: rails
+ghsa: 0000-1111-2222
+title: Already patched
+patched_versions:
+  - \">= 7.0.5\"
+unaffected_versions:
+  - \"< 6.0.0\"
+",
+    )
+    .unwrap();
+
+    let output = fixture.depr(&["audit", "--advisory-db", "ruby-advisory-db"]);
+
+    assert_eq!(output.status.code(), Some(5));
+    let stdout = stdout(&output);
+    let lines = stdout.lines().collect::<Vec<_>>();
+    assert_eq!(
+        lines[0],
+        "rack (2.2.8) CVE-2024-26146: Possible Denial of Service Vulnerability in Rack Header Parsing"
+    );
+    assert!(lines[1].starts_with("    vulnerable since 2023-01-05, "));
+    assert_eq!(
+        lines[2..],
+        [
+            "    patched in ~> 2.0.9, >= 2.0.9.4; ~> 2.2.8, >= 2.2.8.1; >= 3.0.9.1",
+            "    https://github.com/advisories/GHSA-54rr-7fvw-6x8f",
+            "1 vulnerability in 1 gem",
+        ]
+    );
+}
+
+#[test]
+fn tracks_bundler_and_ruby_versions() {
+    let fixture = Fixture::new();
+    let with_ruby = LOCKFILE.replace(
+        "BUNDLED WITH",
+        "RUBY VERSION\n   ruby 3.2.2p53\n\nBUNDLED WITH",
+    );
+    fixture.commit("Gemfile.lock", &with_ruby, "Alice", "2023-01-05");
+    let upgraded = with_ruby
+        .replace("2.4.10", "2.5.3")
+        .replace("3.2.2p53", "3.3.0p0");
+    fixture.commit("Gemfile.lock", &upgraded, "Bob", "2024-01-10");
+
+    let history = stdout(&fixture.depr(&["history", "bundler"]));
+    let diff = stdout(&fixture.depr(&["diff", "HEAD~1"]));
+    let json = stdout(&fixture.depr(&["--format", "json"]));
+
+    let history = history.lines().collect::<Vec<_>>();
+    assert_eq!(history.len(), 2);
+    assert!(history[0].starts_with("2023-01-05  "));
+    assert!(history[0].ends_with("  Alice  added 2.4.10"));
+    assert!(history[1].starts_with("2024-01-10  "));
+    assert!(history[1].ends_with("  Bob  2.4.10 -> 2.5.3"));
+    assert_eq!(
+        diff,
+        "\
+Upgraded:
+    bundler 2.4.10 -> 2.5.3 (minor)
+    ruby 3.2.2p53 -> 3.3.0p0 (minor)
+"
+    );
+    assert!(json.contains(
+        "\"ruby\": {\n    \"version\": \"3.3.0p0\",\n    \"date\": \"2024-01-10T12:00:00Z\","
+    ));
+}
+
+#[test]
+fn audit_matches_requirements_like_rubygems() {
+    // Each gem is patched by a single requirement, so it is only reported
+    // when its version does not satisfy the requirement.
+    let gems = [
+        ("pessimistic-major", "2.9.0", "~> 2"),
+        ("pessimistic-major-next", "3.0", "~> 2"),
+        ("pessimistic-patch", "2.2.10", "~> 2.2.8"),
+        ("pessimistic-patch-next", "2.3.0", "~> 2.2.8"),
+        ("pessimistic-prerelease", "2.2.8.rc1", "~> 2.2.8"),
+        ("pessimistic-from-prerelease", "2.2.8", "~> 2.2.8.rc1"),
+        ("not-equal", "1.0.0", "!= 1.0"),
+        ("equal-padded", "1.0", "= 1.0.0"),
+        ("prerelease-before-release", "1.0.a", "< 1.0"),
+    ];
+    let fixture = Fixture::new();
+    let specs = gems
+        .iter()
+        .map(|(name, version, _)| format!("    {} ({})\n", name, version))
+        .collect::<String>();
+    let lockfile = format!(
+        "GEM\n  remote: https://rubygems.org/\n  specs:\n{}\nPLATFORMS\n  ruby\n",
+        specs
+    );
+    fixture.commit("Gemfile.lock", &lockfile, "Alice", "2023-01-05");
+    let db = fixture.path().join("ruby-advisory-db");
+    for (name, _, patched) in gems {
+        std::fs::create_dir_all(db.join("gems").join(name)).unwrap();
+        std::fs::write(
+            db.join("gems").join(name).join("advisory.yml"),
+            format!(
+                "---\ngem: {}\nghsa: {}\ntitle: Bug\npatched_versions:\n  - \"{}\"\n",
+                name, name, patched
+            ),
+        )
+        .unwrap();
+    }
+
+    let output = fixture.depr(&["audit", "--advisory-db", "ruby-advisory-db"]);
+
+    assert_eq!(output.status.code(), Some(5));
+    let stdout = stdout(&output);
+    let reported = stdout
+        .lines()
+        .filter(|line| !line.starts_with(' '))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        reported,
+        [
+            "pessimistic-major-next (3.0) GHSA-pessimistic-major-next: Bug",
+            "pessimistic-patch-next (2.3.0) GHSA-pessimistic-patch-next: Bug",
+            "pessimistic-prerelease (2.2.8.rc1) GHSA-pessimistic-prerelease: Bug",
+            "not-equal (1.0.0) GHSA-not-equal: Bug",
+            "4 vulnerabilities in 4 gems",
+        ]
+    );
+}
+
+#[test]
+fn audit_rejects_unknown_requirement_operators() {
+    let fixture = Fixture::new();
+    fixture.commit("Gemfile.lock", LOCKFILE, "Alice", "2023-01-05");
+    let db = fixture.path().join("ruby-advisory-db");
+    std::fs::create_dir_all(db.join("gems/rack")).unwrap();
+    std::fs::write(
+        db.join("gems/rack/GHSA-0000-0000-0000.yml"),
+        "---\ngem: rack\nghsa: 0000-0000-0000\ntitle: Typo\npatched_versions:\n  - \"=> 1.0\"\n",
+    )
+    .unwrap();
+
+    let output = fixture.depr(&["audit", "--advisory-db", "ruby-advisory-db"]);
+
+    assert_eq!(output.status.code(), Some(21));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("unknown requirement operator `=>`"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn audit_checks_each_platform_variant() {
+    let fixture = Fixture::new();
+    let variants = LOCKFILE.replace(
+        "    racc (1.7.1)\n",
+        "    nokogiri (1.15.5-arm64-darwin)\n      racc (~> 1.4)\n    racc (1.7.1)\n",
+    );
+    fixture.commit("Gemfile.lock", &variants, "Alice", "2023-01-05");
+    let db = fixture.path().join("ruby-advisory-db");
+    std::fs::create_dir_all(db.join("gems/nokogiri")).unwrap();
+    std::fs::write(
+        db.join("gems/nokogiri/GHSA-xxxx-yyyy-zzzz.yml"),
+        "\
+---
+gem: nokogiri
+ghsa: xxxx-yyyy-zzzz
+title: Use-after-free in libxml2
+patched_versions:
+  - \">= 1.15.5\"
+",
+    )
+    .unwrap();
+
+    let output = fixture.depr(&["audit", "--advisory-db", "ruby-advisory-db"]);
+
+    assert_eq!(output.status.code(), Some(5));
+    let stdout = stdout(&output);
+    let lines = stdout.lines().collect::<Vec<_>>();
+    assert_eq!(
+        lines[0],
+        "nokogiri (1.15.4-x86_64-linux) GHSA-xxxx-yyyy-zzzz: Use-after-free in libxml2"
+    );
+    assert_eq!(
+        lines[2..],
+        ["    patched in >= 1.15.5", "1 vulnerability in 1 gem"]
+    );
+}
+
+#[test]
+fn platforms_lists_when_each_was_added_and_flags_variant_mismatches() {
+    let fixture = Fixture::new();
+    fixture.commit("Gemfile.lock", LOCKFILE, "Alice", "2023-01-05");
+    let arm = LOCKFILE
+        .replace(
+            "    racc (1.7.1)\n",
+            "    nokogiri (1.15.5-arm64-darwin)\n      racc (~> 1.4)\n    racc (1.7.1)\n",
+        )
+        .replace(
+            "    rack (2.2.8)\n",
+            "    rack (2.2.8)\n    sqlite3 (1.7.0-arm64-darwin)\n    sqlite3 (1.7.0-x86_64-linux)\n",
+        )
+        .replace("  x86_64-linux\n", "  arm64-darwin\n  x86_64-linux\n");
+    fixture.commit("Gemfile.lock", &arm, "Bob", "2023-06-05");
+
+    let output = stdout(&fixture.depr(&["platforms"]));
+    let lines = output.lines().collect::<Vec<_>>();
+
+    assert_eq!(lines.len(), 6);
+    assert_eq!(lines[0], "Platforms:");
+    assert!(lines[1].starts_with("    arm64-darwin  added 2023-06-05  "));
+    assert!(lines[1].ends_with("  Bob"));
+    assert!(lines[2].starts_with("    x86_64-linux  added 2023-01-05  "));
+    assert!(lines[2].ends_with("  Alice"));
+    assert_eq!(
+        lines[3..],
+        [
+            "Platform variants:",
+            "    nokogiri 1.15.4 on x86_64-linux, 1.15.5 on arm64-darwin  versions differ",
+            "    sqlite3 (1.7.0) arm64-darwin, x86_64-linux",
+        ]
+    );
+}
+
+#[test]
+fn platforms_credits_the_branch_commit_rather_than_the_merge() {
+    let fixture = Fixture::new();
+    let base = fixture.commit("Gemfile.lock", LOCKFILE, "Alice", "2023-01-05");
+    let arm = LOCKFILE.replace("  x86_64-linux\n", "  arm64-darwin\n  x86_64-linux\n");
+    let branch = fixture.branch(base, "Gemfile.lock", &arm, "Bob", "2023-02-01");
+    let bumped = LOCKFILE.replace("rack (2.2.8)", "rack (2.2.9)");
+    fixture.commit("Gemfile.lock", &bumped, "Carol", "2023-03-01");
+    let merged = arm.replace("rack (2.2.8)", "rack (2.2.9)");
+    fixture.merge(branch, "Gemfile.lock", &merged, "Merger", "2023-04-01");
+
+    let output = stdout(&fixture.depr(&["platforms"]));
+
+    assert_eq!(
+        output,
+        format!(
+            "\
+Platforms:
+    arm64-darwin  added 2023-02-01  {}  Bob
+    x86_64-linux  added 2023-01-05  {}  Alice
+Platform variants:
+    nokogiri (1.15.4) x86_64-linux
+",
+            &branch.to_string()[..7],
+            &base.to_string()[..7],
+        )
+    );
+}
+
+#[test]
+fn history_lists_every_version_of_a_gem() {
+    let fixture = Fixture::new();
+    let added = fixture.commit("Gemfile.lock", LOCKFILE, "Alice", "2023-01-05");
+    let unrelated = LOCKFILE.replace("rack (2.2.8)", "rack (2.2.9)");
+    fixture.commit("Gemfile.lock", &unrelated, "Bob", "2023-02-01");
+    let bumped = unrelated.replace("rails (7.0.4)", "rails (7.1.1)");
+    let bump = fixture.commit("Gemfile.lock", &bumped, "Bob", "2023-06-05");
+    let removed = bumped.replace("    rails (7.1.1)\n      actionpack (= 7.0.4)\n", "");
+    let removal = fixture.commit("Gemfile.lock", &removed, "Carol", "2023-09-05");
+
+    let output = stdout(&fixture.depr(&["history", "rails"]));
+
+    assert_eq!(
+        output,
+        format!(
+            "\
+2023-01-05  {}  Alice  added 7.0.4
+2023-06-05  {}  Bob  7.0.4 -> 7.1.1
+2023-09-05  {}  Carol  removed 7.1.1
+",
+            &added.to_string()[..7],
+            &bump.to_string()[..7],
+            &removal.to_string()[..7],
+        )
+    );
+}
+
+#[test]
+fn history_credits_the_branch_commit_rather_than_the_merge() {
+    let fixture = Fixture::new();
+    let base = fixture.commit("Gemfile.lock", LOCKFILE, "Alice", "2023-01-05");
+    let bumped = LOCKFILE.replace("rails (7.0.4)", "rails (7.1.1)");
+    let bump = fixture.branch(base, "Gemfile.lock", &bumped, "Bob", "2023-02-01");
+    fixture.merge(bump, "Gemfile.lock", &bumped, "Merger", "2023-04-01");
+
+    let output = stdout(&fixture.depr(&["history", "rails"]));
+
+    assert_eq!(
+        output,
+        format!(
+            "\
+2023-01-05  {}  Alice  added 7.0.4
+2023-02-01  {}  Bob  7.0.4 -> 7.1.1
+",
+            &base.to_string()[..7],
+            &bump.to_string()[..7],
+        )
+    );
+}
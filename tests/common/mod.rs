@@ -0,0 +1,140 @@
+#![allow(dead_code)]
+
+use chrono::{NaiveDate, NaiveDateTime};
+use git2::{Oid, Repository, Signature, Time};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use tempfile::TempDir;
+
+/// A throwaway git repository to run depr against.
+pub struct Fixture {
+    dir: TempDir,
+    repo: Repository,
+}
+
+impl Fixture {
+    pub fn new() -> Fixture {
+        let dir = TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        Fixture { dir, repo }
+    }
+
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    pub fn repo(&self) -> &Repository {
+        &self.repo
+    }
+
+    /// Writes `contents` to `path` and commits it at noon UTC on `date`, or
+    /// at the given time for dates like `2023-01-05 08:00`.
+    pub fn commit(&self, path: &str, contents: &str, author: &str, date: &str) -> Oid {
+        let head = self.head().into_iter().collect::<Vec<_>>();
+        self.commit_with_parents(path, contents, author, date, &head, Some("HEAD"))
+    }
+
+    /// Commits on a side branch starting at `parent`, leaving HEAD alone.
+    pub fn branch(&self, parent: Oid, path: &str, contents: &str, author: &str, date: &str) -> Oid {
+        self.commit_with_parents(path, contents, author, date, &[parent], None)
+    }
+
+    /// Merges `other` into HEAD with a merge commit whose lockfile is
+    /// `contents`, like `git merge --no-ff`.
+    pub fn merge(&self, other: Oid, path: &str, contents: &str, author: &str, date: &str) -> Oid {
+        let parents = [self.head().unwrap(), other];
+        self.commit_with_parents(path, contents, author, date, &parents, Some("HEAD"))
+    }
+
+    fn head(&self) -> Option<Oid> {
+        self.repo.head().ok().and_then(|head| head.target())
+    }
+
+    fn commit_with_parents(
+        &self,
+        path: &str,
+        contents: &str,
+        author: &str,
+        date: &str,
+        parents: &[Oid],
+        update_ref: Option<&str>,
+    ) -> Oid {
+        let file = self.path().join(path);
+        fs::create_dir_all(file.parent().unwrap()).unwrap();
+        fs::write(&file, contents).unwrap();
+
+        let mut index = self.repo.index().unwrap();
+        index.add_path(Path::new(path)).unwrap();
+        index.write().unwrap();
+        let tree = self.repo.find_tree(index.write_tree().unwrap()).unwrap();
+
+        let seconds = NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M")
+            .unwrap_or_else(|_| {
+                NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                    .unwrap()
+                    .and_hms_opt(12, 0, 0)
+                    .unwrap()
+            })
+            .timestamp();
+        let email = format!("{}@example.com", author.to_lowercase());
+        let signature = Signature::new(author, &email, &Time::new(seconds, 0)).unwrap();
+
+        let parents = parents
+            .iter()
+            .map(|parent| self.repo.find_commit(*parent).unwrap())
+            .collect::<Vec<_>>();
+        let parents = parents.iter().collect::<Vec<_>>();
+        self.repo
+            .commit(
+                update_ref,
+                &signature,
+                &signature,
+                &format!("Update {}", path),
+                &tree,
+                &parents,
+            )
+            .unwrap()
+    }
+
+    /// Runs depr from the root of the repository.
+    pub fn depr(&self, args: &[&str]) -> Output {
+        self.depr_in(self.path().to_path_buf(), args)
+    }
+
+    pub fn depr_in(&self, dir: PathBuf, args: &[&str]) -> Output {
+        Command::new(env!("CARGO_BIN_EXE_depr"))
+            .args(args)
+            .current_dir(dir)
+            .env("TZ", "UTC")
+            .output()
+            .unwrap()
+    }
+}
+
+pub fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+pub const LOCKFILE: &str = "\
+GEM
+  remote: https://rubygems.org/
+  specs:
+    actionpack (7.0.4)
+      rack (~> 2.0, >= 2.2.0)
+    nokogiri (1.15.4-x86_64-linux)
+      racc (~> 1.4)
+    racc (1.7.1)
+    rack (2.2.8)
+    rails (7.0.4)
+      actionpack (= 7.0.4)
+
+PLATFORMS
+  x86_64-linux
+
+DEPENDENCIES
+  rails (= 7.0.4)
+
+BUNDLED WITH
+   2.4.10
+";
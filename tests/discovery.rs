@@ -0,0 +1,127 @@
+mod common;
+
+use common::{stdout, Fixture, LOCKFILE};
+use tempfile::TempDir;
+
+const EXPECTED: &str = "\
+Updated 2023-01-05:
+    actionpack (7.0.4)
+    nokogiri (1.15.4-x86_64-linux)
+    racc (1.7.1)
+    rack (2.2.8)
+    rails (7.0.4)
+    Bundler 2.4.10
+";
+
+#[test]
+fn finds_a_lockfile_in_a_monorepo_service() {
+    let fixture = Fixture::new();
+    fixture.commit("services/api/Gemfile.lock", LOCKFILE, "Alice", "2023-01-05");
+
+    let from_service = fixture.depr_in(fixture.path().join("services/api"), &[]);
+    let from_root = fixture.depr(&["services/api"]);
+
+    assert_eq!(stdout(&from_service), EXPECTED);
+    assert_eq!(stdout(&from_root), EXPECTED);
+}
+
+#[test]
+fn finds_the_repository_from_a_linked_worktree() {
+    let fixture = Fixture::new();
+    fixture.commit("Gemfile.lock", LOCKFILE, "Alice", "2023-01-05");
+    let parent = TempDir::new().unwrap();
+    let worktree = parent.path().join("linked");
+    fixture.repo().worktree("linked", &worktree, None).unwrap();
+
+    let output = fixture.depr_in(worktree, &[]);
+
+    assert_eq!(stdout(&output), EXPECTED);
+}
+
+#[test]
+fn scans_every_tracked_lockfile_and_compares_shared_gems() {
+    let fixture = Fixture::new();
+    fixture.commit("services/api/Gemfile.lock", LOCKFILE, "Alice", "2023-01-05");
+    let next = LOCKFILE.replace("rails (7.0.4)", "rails (7.1.1)");
+    fixture.commit("services/web/gems.locked", &next, "Bob", "2023-06-05");
+    fixture.commit("README.lock", "", "Bob", "2023-06-05");
+
+    let output = stdout(&fixture.depr(&["--recursive", "--by", "gem"]));
+
+    assert!(output.starts_with("== services/api/Gemfile.lock ==\n"));
+    assert!(output.contains("\n== services/web/gems.locked ==\n"));
+    assert!(!output.contains("README.lock"));
+    assert!(output.ends_with(
+        "\
+Shared gems at different versions:
+rails
+    services/api/Gemfile.lock  7.0.4  2023-01-05  furthest behind
+    services/web/gems.locked   7.1.1  2023-06-05
+"
+    ));
+}
+
+#[test]
+fn scans_past_lockfiles_that_are_only_staged_or_deleted() {
+    let fixture = Fixture::new();
+    fixture.commit("services/api/Gemfile.lock", LOCKFILE, "Alice", "2023-01-05");
+    fixture.commit("services/old/Gemfile.lock", LOCKFILE, "Alice", "2023-01-05");
+    std::fs::remove_file(fixture.path().join("services/old/Gemfile.lock")).unwrap();
+    let staged = fixture.path().join("services/web/gems.locked");
+    std::fs::create_dir_all(staged.parent().unwrap()).unwrap();
+    std::fs::write(&staged, LOCKFILE).unwrap();
+    let mut index = fixture.repo().index().unwrap();
+    index
+        .add_path(std::path::Path::new("services/web/gems.locked"))
+        .unwrap();
+    index.write().unwrap();
+
+    let output = fixture.depr(&["--recursive"]);
+
+    assert!(output.status.success());
+    assert_eq!(
+        stdout(&output),
+        format!("== services/api/Gemfile.lock ==\n{}", EXPECTED)
+    );
+}
+
+fn stderr(output: &std::process::Output) -> String {
+    String::from_utf8(output.stderr.clone()).unwrap()
+}
+
+#[test]
+fn exits_with_a_distinct_code_for_each_error() {
+    let fixture = Fixture::new();
+    fixture.commit(
+        "Gemfile",
+        "source \"https://rubygems.org\"\n",
+        "Alice",
+        "2023-01-05",
+    );
+
+    let empty = Fixture::new();
+    std::fs::write(empty.path().join("Gemfile.lock"), LOCKFILE).unwrap();
+    let no_commits = empty.depr(&["history", "rails"]);
+    assert_eq!(no_commits.status.code(), Some(13));
+    assert!(stderr(&no_commits).contains("is not tracked by git"));
+
+    let missing = fixture.depr(&[]);
+    assert_eq!(missing.status.code(), Some(10));
+    assert!(stderr(&missing).contains("no lockfile found at "));
+
+    std::fs::write(fixture.path().join("Gemfile.lock"), LOCKFILE).unwrap();
+    let untracked = fixture.depr(&[]);
+    assert_eq!(untracked.status.code(), Some(13));
+    assert!(stderr(&untracked).contains("is not tracked by git"));
+
+    fixture.commit("Gemfile.lock", LOCKFILE, "Alice", "2023-01-05");
+    let unknown = fixture.depr(&["--rev", "no-such-branch"]);
+    assert_eq!(unknown.status.code(), Some(16));
+    assert!(stderr(&unknown).contains("unknown revision `no-such-branch`"));
+
+    let outside = TempDir::new().unwrap();
+    std::fs::write(outside.path().join("Gemfile.lock"), LOCKFILE).unwrap();
+    let not_a_repository = fixture.depr_in(outside.path().to_path_buf(), &[]);
+    assert_eq!(not_a_repository.status.code(), Some(12));
+    assert!(stderr(&not_a_repository).contains("is not a git repository"));
+}
@@ -0,0 +1,92 @@
+mod common;
+
+use common::{stdout, Fixture, LOCKFILE};
+use serde_json::Value;
+
+const VERSIONS: &str = "\
+GEM
+  remote: https://rubygems.org/
+  specs:
+    google-protobuf (3.25.1.12)
+    nokogiri (1.15.4-x86_64-linux)
+      racc (~> 1.4)
+    racc (2.0)
+    rails (7.1.0.rc1)
+
+PLATFORMS
+  x86_64-linux
+
+DEPENDENCIES
+  rails (= 7.1.0.rc1)
+";
+
+#[test]
+fn parses_every_kind_of_version() {
+    let fixture = Fixture::new();
+    fixture.commit("Gemfile.lock", VERSIONS, "Alice", "2023-01-05");
+
+    let json: Value = serde_json::from_str(&stdout(&fixture.depr(&["--format", "json"]))).unwrap();
+    let gems = json["gems"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|gem| {
+            (
+                gem["name"].as_str().unwrap(),
+                gem["version"].as_str().unwrap(),
+                gem["platform"].as_str(),
+                gem["line"].as_u64().unwrap(),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        gems,
+        [
+            ("google-protobuf", "3.25.1.12", None, 4),
+            ("nokogiri", "1.15.4", Some("x86_64-linux"), 5),
+            ("racc", "2.0", None, 7),
+            ("rails", "7.1.0.rc1", None, 8),
+        ]
+    );
+}
+
+#[test]
+fn reports_dependency_constraints_only_when_asked() {
+    let fixture = Fixture::new();
+    fixture.commit("Gemfile.lock", LOCKFILE, "Alice", "2023-01-05");
+    let tightened = LOCKFILE.replace(">= 2.2.0", ">= 2.2.4");
+    fixture.commit("Gemfile.lock", &tightened, "Bob", "2023-06-05");
+
+    let specs = stdout(&fixture.depr(&[]));
+    let constraints = stdout(&fixture.depr(&["--constraints"]));
+
+    assert_eq!(
+        specs,
+        "\
+Updated 2023-01-05:
+    actionpack (7.0.4)
+    nokogiri (1.15.4-x86_64-linux)
+    racc (1.7.1)
+    rack (2.2.8)
+    rails (7.0.4)
+    Bundler 2.4.10
+"
+    );
+    assert_eq!(
+        constraints,
+        "\
+Updated 2023-01-05:
+    actionpack (7.0.4)
+    nokogiri (1.15.4-x86_64-linux)
+      racc (~> 1.4) (required by nokogiri)
+    racc (1.7.1)
+    rack (2.2.8)
+    rails (7.0.4)
+      actionpack (= 7.0.4) (required by rails)
+    Bundler 2.4.10
+Updated 2023-06-05:
+      rack (~> 2.0, >= 2.2.4) (required by actionpack)
+"
+    );
+}
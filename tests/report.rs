@@ -0,0 +1,595 @@
+mod common;
+
+use common::{stdout, Fixture, LOCKFILE};
+use serde_json::{json, Value};
+
+#[test]
+fn reports_every_spec_under_the_date_it_changed() {
+    let fixture = Fixture::new();
+    fixture.commit("Gemfile.lock", LOCKFILE, "Alice", "2023-01-05");
+    let bumped = LOCKFILE.replace("rails (7.0.4)", "rails (7.1.1)");
+    fixture.commit("Gemfile.lock", &bumped, "Bob", "2023-06-05");
+
+    let output = fixture.depr(&[]);
+
+    assert!(output.status.success());
+    assert_eq!(
+        stdout(&output),
+        "\
+Updated 2023-01-05:
+    actionpack (7.0.4)
+    nokogiri (1.15.4-x86_64-linux)
+    racc (1.7.1)
+    rack (2.2.8)
+    Bundler 2.4.10
+Updated 2023-06-05:
+    rails (7.1.1)
+"
+    );
+}
+
+#[test]
+fn attributes_each_spec_once_when_hunks_share_a_date() {
+    let fixture = Fixture::new();
+    fixture.commit("Gemfile.lock", LOCKFILE, "Alice", "2023-01-05");
+    let first = LOCKFILE.replace("actionpack (7.0.4)", "actionpack (7.0.5)");
+    fixture.commit("Gemfile.lock", &first, "Alice", "2023-06-05");
+    let second = first.replace("rack (2.2.8)", "rack (2.2.9)");
+    fixture.commit("Gemfile.lock", &second, "Bob", "2023-06-05");
+
+    let output = stdout(&fixture.depr(&[]));
+
+    assert_eq!(
+        output,
+        "\
+Updated 2023-01-05:
+    nokogiri (1.15.4-x86_64-linux)
+    racc (1.7.1)
+    rails (7.0.4)
+    Bundler 2.4.10
+Updated 2023-06-05:
+    actionpack (7.0.5)
+    rack (2.2.9)
+"
+    );
+}
+
+#[test]
+fn analyzes_the_lockfile_at_a_revision() {
+    let fixture = Fixture::new();
+    let release = fixture.commit("Gemfile.lock", LOCKFILE, "Alice", "2023-01-05");
+    let bumped = LOCKFILE.replace("rails (7.0.4)", "rails (7.1.1)");
+    fixture.commit("Gemfile.lock", &bumped, "Bob", "2023-06-05");
+    let commit = fixture.repo().find_commit(release).unwrap();
+    fixture
+        .repo()
+        .tag_lightweight("v1.0.0", commit.as_object(), false)
+        .unwrap();
+
+    let output = stdout(&fixture.depr(&["--rev", "v1.0.0", "--by", "gem"]));
+
+    assert!(output.contains("rails       7.0.4   2023-01-05"));
+    assert!(!output.contains("7.1.1"));
+}
+
+#[test]
+fn sorts_the_gem_report_by_age() {
+    let fixture = Fixture::new();
+    let first = fixture.commit("Gemfile.lock", LOCKFILE, "Alice", "2023-01-05");
+    let rails = LOCKFILE.replace("rails (7.0.4)", "rails (7.1.1)");
+    let second = fixture.commit("Gemfile.lock", &rails, "Bob", "2023-03-01");
+    let rack = rails.replace("rack (2.2.8)", "rack (2.2.9)");
+    let third = fixture.commit("Gemfile.lock", &rack, "Bob", "2023-06-05");
+    let (first, second, third) = (
+        &first.to_string()[..7],
+        &second.to_string()[..7],
+        &third.to_string()[..7],
+    );
+
+    let by_name = stdout(&fixture.depr(&["--by", "gem"]));
+    let by_age = stdout(&fixture.depr(&["--by", "gem", "--sort", "age"]));
+
+    assert_eq!(
+        by_name,
+        format!(
+            "\
+actionpack  7.0.4   2023-01-05  {first}
+nokogiri    1.15.4  2023-01-05  {first}
+racc        1.7.1   2023-01-05  {first}
+rack        2.2.9   2023-06-05  {third}
+rails       7.1.1   2023-03-01  {second}
+Bundler     2.4.10  2023-01-05  {first}
+"
+        )
+    );
+    assert_eq!(
+        by_age,
+        format!(
+            "\
+actionpack  7.0.4   2023-01-05  {first}
+nokogiri    1.15.4  2023-01-05  {first}
+racc        1.7.1   2023-01-05  {first}
+rails       7.1.1   2023-03-01  {second}
+rack        2.2.9   2023-06-05  {third}
+Bundler     2.4.10  2023-01-05  {first}
+"
+        )
+    );
+}
+
+#[test]
+fn groups_gems_by_the_author_who_last_changed_them() {
+    let fixture = Fixture::new();
+    fixture.commit("Gemfile.lock", LOCKFILE, "Alice", "2023-01-05");
+    let bumped = LOCKFILE.replace("rails (7.0.4)", "rails (7.1.1)");
+    fixture.commit("Gemfile.lock", &bumped, "Bob", "2023-06-05");
+
+    let output = stdout(&fixture.depr(&["--by", "author"]));
+
+    assert_eq!(
+        output,
+        "\
+Alice <alice@example.com>:
+    actionpack (7.0.4) 2023-01-05
+    nokogiri (1.15.4) 2023-01-05
+    racc (1.7.1) 2023-01-05
+    rack (2.2.8) 2023-01-05
+    Bundler 2.4.10 2023-01-05
+Bob <bob@example.com>:
+    rails (7.1.1) 2023-06-05
+"
+    );
+}
+
+#[test]
+fn lists_bundler_and_ruby_after_the_gems_in_every_grouping() {
+    let fixture = Fixture::new();
+    let lockfile = LOCKFILE.replace(
+        "BUNDLED WITH",
+        "RUBY VERSION\n   ruby 3.2.2p53\n\nBUNDLED WITH",
+    );
+    fixture.commit("Gemfile.lock", &lockfile, "Alice", "2023-01-05");
+    let bumped = lockfile.replace("   2.4.10", "   2.5.3");
+    fixture.commit("Gemfile.lock", &bumped, "Bob", "2023-06-05");
+
+    let by_gem = stdout(&fixture.depr(&["--by", "gem"]));
+    let by_author = stdout(&fixture.depr(&["--by", "author"]));
+    let by_source = stdout(&fixture.depr(&["--by", "source"]));
+
+    let rows = by_gem.lines().collect::<Vec<_>>();
+    assert_eq!(rows.len(), 7);
+    assert!(rows[5].starts_with("Ruby        3.2.2p53  2023-01-05  "));
+    assert!(rows[6].starts_with("Bundler     2.5.3     2023-06-05  "));
+    assert!(by_author.ends_with(
+        "    Ruby 3.2.2p53 2023-01-05\nBob <bob@example.com>:\n    Bundler 2.5.3 2023-06-05\n"
+    ));
+    assert!(by_source.ends_with(
+        "\
+RUBY VERSION:
+    Ruby 3.2.2p53 2023-01-05
+BUNDLED WITH:
+    Bundler 2.5.3 2023-06-05
+"
+    ));
+}
+
+#[test]
+fn canonicalizes_authors_through_the_mailmap() {
+    let fixture = Fixture::new();
+    fixture.commit(
+        ".mailmap",
+        "Alice <alice@example.com> Al <al@example.com>\n",
+        "Alice",
+        "2023-01-01",
+    );
+    fixture.commit("Gemfile.lock", LOCKFILE, "Alice", "2023-01-05");
+    let bumped = LOCKFILE.replace("rails (7.0.4)", "rails (7.1.1)");
+    fixture.commit("Gemfile.lock", &bumped, "Al", "2023-06-05");
+
+    let output = stdout(&fixture.depr(&["--by", "author"]));
+
+    assert!(output.starts_with("Alice <alice@example.com>:\n"));
+    assert!(output.contains("    rails (7.1.1) 2023-06-05\n"));
+    assert!(!output.contains("Al <al@example.com>"));
+}
+
+fn resorted_fixture() -> (Fixture, git2::Oid) {
+    let fixture = Fixture::new();
+    fixture.commit("Gemfile.lock", LOCKFILE, "Alice", "2023-01-05");
+    let bumped = LOCKFILE.replace("rails (7.0.4)", "rails (7.1.1)");
+    fixture.commit("Gemfile.lock", &bumped, "Bob", "2023-06-05");
+    let resorted = bumped.replace(
+        "    racc (1.7.1)\n    rack (2.2.8)\n",
+        "    rack (2.2.8)\n    racc (1.7.1)\n",
+    );
+    let reformat = fixture.commit("Gemfile.lock", &resorted, "Bob", "2023-09-01");
+    (fixture, reformat)
+}
+
+const RESORTED: &str = "\
+Updated 2023-01-05:
+    actionpack (7.0.4)
+    nokogiri (1.15.4-x86_64-linux)
+    rack (2.2.8)
+    racc (1.7.1)
+    Bundler 2.4.10
+Updated 2023-06-05:
+    rails (7.1.1)
+";
+
+#[test]
+fn reports_lines_changed_in_the_working_tree_as_not_committed() {
+    let fixture = Fixture::new();
+    fixture.commit("Gemfile.lock", LOCKFILE, "Alice", "2023-01-05");
+    let bumped = LOCKFILE.replace("rails (7.0.4)", "rails (7.1.1)");
+    fixture.commit("Gemfile.lock", &bumped, "Bob", "2023-06-05");
+    let dirty = format!(
+        "PATH\n  remote: vendor/zzz\n  specs:\n    zzz (1.0)\n\n{}",
+        bumped.replace("rack (2.2.8)", "rack (2.2.9)")
+    );
+    std::fs::write(fixture.path().join("Gemfile.lock"), dirty).unwrap();
+
+    let expected = "\
+Updated 2023-01-05:
+    actionpack (7.0.4)
+    nokogiri (1.15.4-x86_64-linux)
+    racc (1.7.1)
+    Bundler 2.4.10
+Updated 2023-06-05:
+    rails (7.1.1)
+Not committed yet:
+    zzz (1.0)
+    rack (2.2.9)
+";
+    assert_eq!(stdout(&fixture.depr(&[])), expected);
+    assert_eq!(stdout(&fixture.depr(&["--dating", "version"])), expected);
+    assert_eq!(
+        stdout(&fixture.depr(&["--ignore-unchanged-versions"])),
+        expected
+    );
+}
+
+#[test]
+fn exits_with_the_threshold_each_gem_crossed() {
+    let (fixture, _) = resorted_fixture();
+
+    let fresh = fixture.depr(&["--max-age", "1000y", "--warn-age", "100y"]);
+    assert_eq!(fresh.status.code(), Some(0));
+    assert!(fresh.stderr.is_empty());
+
+    let warned = fixture.depr(&["--max-age", "1000y", "--warn-age", "1d"]);
+    assert_eq!(warned.status.code(), Some(3));
+    let stderr = String::from_utf8(warned.stderr).unwrap();
+    let lines = stderr.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 6);
+    assert_eq!(lines[0], "5 gems older than --warn-age 1d:");
+    assert!(lines[1].starts_with("    actionpack (7.0.4) last updated 2023-01-05, "));
+    assert!(lines[5].starts_with("    rails (7.1.1) last updated 2023-06-05, "));
+    assert!(lines[5].ends_with(" days ago"));
+
+    let failed = fixture.depr(&["--max-age", "2w", "--warn-age", "1d"]);
+    assert_eq!(failed.status.code(), Some(4));
+    let stderr = String::from_utf8(failed.stderr).unwrap();
+    assert!(stderr.starts_with("5 gems older than --max-age 2w:\n"));
+    assert!(!stderr.contains("--warn-age"));
+}
+
+#[test]
+fn rejects_ages_that_are_not_positive() {
+    let (fixture, _) = resorted_fixture();
+
+    for args in [["--max-age=-5d"], ["--warn-age=0d"]] {
+        let output = fixture.depr(&args);
+
+        assert_eq!(output.status.code(), Some(2));
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(stderr.contains("is not a positive age"), "{}", stderr);
+    }
+}
+
+#[test]
+fn rejects_ages_too_long_to_count_in_seconds() {
+    let (fixture, _) = resorted_fixture();
+
+    for age in ["99999999999999999y", "200000000000000d"] {
+        let output = fixture.depr(&["--max-age", age]);
+
+        assert_eq!(output.status.code(), Some(2));
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(stderr.contains("is too long an age"), "{}", stderr);
+    }
+}
+
+#[test]
+fn skips_commits_passed_to_ignore_rev() {
+    let (fixture, reformat) = resorted_fixture();
+
+    let plain = stdout(&fixture.depr(&[]));
+    let ignored = stdout(&fixture.depr(&["--ignore-rev", &reformat.to_string()]));
+
+    assert!(plain.contains("Updated 2023-09-01:"));
+    assert_eq!(ignored, RESORTED);
+}
+
+#[test]
+fn skips_commits_listed_in_git_blame_ignore_revs() {
+    let (fixture, reformat) = resorted_fixture();
+    std::fs::write(
+        fixture.path().join(".git-blame-ignore-revs"),
+        format!("# Re-sort specs\n{}\n", reformat),
+    )
+    .unwrap();
+
+    assert_eq!(stdout(&fixture.depr(&[])), RESORTED);
+}
+
+#[test]
+fn skips_commits_that_did_not_change_a_version() {
+    let (fixture, _) = resorted_fixture();
+
+    let output = stdout(&fixture.depr(&["--ignore-unchanged-versions"]));
+
+    assert_eq!(output, RESORTED);
+}
+
+#[test]
+fn dates_gems_by_their_last_version_change() {
+    let (fixture, _) = resorted_fixture();
+
+    let output = stdout(&fixture.depr(&["--dating", "version"]));
+
+    assert_eq!(output, RESORTED);
+}
+
+#[test]
+fn dates_a_merged_version_by_the_branch_commit() {
+    let fixture = Fixture::new();
+    let base = fixture.commit("Gemfile.lock", LOCKFILE, "Alice", "2023-01-05");
+    let bumped = LOCKFILE.replace("rails (7.0.4)", "rails (7.1.1)");
+    let bump = fixture.branch(base, "Gemfile.lock", &bumped, "Bob", "2023-02-01");
+    fixture.merge(bump, "Gemfile.lock", &bumped, "Merger", "2023-04-01");
+
+    let output = stdout(&fixture.depr(&["--dating", "version", "--by", "gem"]));
+
+    assert!(output.contains(&format!(
+        "rails       7.1.1   2023-02-01  {}",
+        &bump.to_string()[..7]
+    )));
+}
+
+#[test]
+fn dates_by_version_past_ignored_commits() {
+    let fixture = Fixture::new();
+    fixture.commit("Gemfile.lock", LOCKFILE, "Alice", "2023-01-05");
+    let bumped = LOCKFILE.replace("rails (7.0.4)", "rails (7.1.1)");
+    let bump = fixture.commit("Gemfile.lock", &bumped, "Bob", "2023-06-05");
+
+    let output = stdout(&fixture.depr(&["--dating", "version", "--ignore-rev", &bump.to_string()]));
+
+    assert!(!output.contains("Updated 2023-06-05:"), "{}", output);
+    assert!(output.contains("    rails (7.1.1)\n"), "{}", output);
+}
+
+#[test]
+fn groups_days_by_the_time_of_day_a_date_format_shows() {
+    let fixture = Fixture::new();
+    fixture.commit("Gemfile.lock", LOCKFILE, "Alice", "2023-01-05 08:00");
+    let bumped = LOCKFILE.replace("rails (7.0.4)", "rails (7.1.1)");
+    fixture.commit("Gemfile.lock", &bumped, "Bob", "2023-01-05 18:00");
+
+    let output = stdout(&fixture.depr(&["--date-format", "%Y-%m-%d %H:%M"]));
+
+    assert_eq!(
+        output,
+        "\
+Updated 2023-01-05 08:00:
+    actionpack (7.0.4)
+    nokogiri (1.15.4-x86_64-linux)
+    racc (1.7.1)
+    rack (2.2.8)
+    Bundler 2.4.10
+Updated 2023-01-05 18:00:
+    rails (7.1.1)
+"
+    );
+}
+
+#[test]
+fn merges_days_a_coarser_date_format_shows_alike() {
+    let fixture = Fixture::new();
+    fixture.commit("Gemfile.lock", LOCKFILE, "Alice", "2023-01-05");
+    let bumped = LOCKFILE.replace("rails (7.0.4)", "rails (7.1.1)");
+    fixture.commit("Gemfile.lock", &bumped, "Bob", "2023-01-20");
+    let rack = bumped.replace("rack (2.2.8)", "rack (2.2.9)");
+    fixture.commit("Gemfile.lock", &rack, "Bob", "2023-02-01");
+
+    let output = stdout(&fixture.depr(&["--date-format", "%Y-%m"]));
+
+    assert_eq!(
+        output,
+        "\
+Updated 2023-01:
+    actionpack (7.0.4)
+    nokogiri (1.15.4-x86_64-linux)
+    racc (1.7.1)
+    rails (7.1.1)
+    Bundler 2.4.10
+Updated 2023-02:
+    rack (2.2.9)
+"
+    );
+}
+
+#[test]
+fn groups_by_month_in_the_requested_time_zone() {
+    let fixture = Fixture::new();
+    fixture.commit("Gemfile.lock", LOCKFILE, "Alice", "2023-01-31");
+    let bumped = LOCKFILE.replace("rails (7.0.4)", "rails (7.1.1)");
+    fixture.commit("Gemfile.lock", &bumped, "Bob", "2023-02-10");
+
+    let utc = stdout(&fixture.depr(&["--group", "month", "--tz", "utc"]));
+    let kiritimati = stdout(&fixture.depr(&["--group", "month", "--tz", "Pacific/Kiritimati"]));
+
+    assert!(utc.starts_with("Updated 2023-01:\n"));
+    assert_eq!(
+        kiritimati,
+        "\
+Updated 2023-02:
+    actionpack (7.0.4)
+    nokogiri (1.15.4-x86_64-linux)
+    racc (1.7.1)
+    rack (2.2.8)
+    rails (7.1.1)
+    Bundler 2.4.10
+"
+    );
+}
+
+const SOURCES: &str = "\
+GIT
+  remote: https://github.com/rails/rails.git
+  revision: 1111111111111111111111111111111111111111
+  branch: main
+  specs:
+    rails (7.2.0.alpha)
+      rack (>= 2.2.4)
+
+PATH
+  remote: engines/admin
+  specs:
+    admin (0.1.0)
+
+GEM
+  remote: https://rubygems.org/
+  specs:
+    rack (2.2.8)
+
+GEM
+  remote: https://gems.example.com/
+  specs:
+    internal (1.0.0)
+
+PLATFORMS
+  ruby
+
+DEPENDENCIES
+  admin!
+  internal!
+  rails!
+";
+
+#[test]
+fn groups_by_source_with_git_revision_dates() {
+    let fixture = Fixture::new();
+    fixture.commit("Gemfile.lock", SOURCES, "Alice", "2023-01-05");
+    let bumped = SOURCES.replace(
+        "1111111111111111111111111111111111111111",
+        "2222222222222222222222222222222222222222",
+    );
+    fixture.commit("Gemfile.lock", &bumped, "Bob", "2023-03-01");
+
+    let text = stdout(&fixture.depr(&["--by", "source"]));
+    let json = stdout(&fixture.depr(&["--format", "json"]));
+
+    assert_eq!(
+        text,
+        "\
+GIT https://github.com/rails/rails.git (branch main):
+    revision 2222222 pinned 2023-03-01
+    rails (7.2.0.alpha) 2023-01-05
+PATH engines/admin:
+    admin (0.1.0) 2023-01-05
+GEM https://rubygems.org/:
+    rack (2.2.8) 2023-01-05
+GEM https://gems.example.com/:
+    internal (1.0.0) 2023-01-05
+"
+    );
+    assert!(json.contains(
+        r#"      "source": {
+        "type": "git",
+        "remotes": [
+          "https://github.com/rails/rails.git"
+        ],
+        "revision": "2222222222222222222222222222222222222222",
+        "revision_date": "2023-03-01T12:00:00Z",
+        "branch": "main",
+        "tag": null
+      },"#
+    ));
+}
+
+#[test]
+fn writes_every_field_of_the_json_report() {
+    let fixture = Fixture::new();
+    let lockfile = "\
+GEM
+  remote: https://rubygems.org/
+  specs:
+    nokogiri (1.15.4-x86_64-linux)
+    rack (2.2.8)
+
+PLATFORMS
+  x86_64-linux
+
+DEPENDENCIES
+  nokogiri
+  rack
+
+RUBY VERSION
+   ruby 3.2.2p53
+
+BUNDLED WITH
+   2.4.10
+";
+    let commit = fixture
+        .commit("Gemfile.lock", lockfile, "Alice", "2023-01-05")
+        .to_string();
+
+    let output = stdout(&fixture.depr(&["--format", "json"]));
+    let report: Value = serde_json::from_str(&output).unwrap();
+
+    let gem = |name: &str, version: &str, platform: Option<&str>, line: usize| {
+        json!({
+            "name": name,
+            "version": version,
+            "platform": platform,
+            "source": {
+                "type": "gem",
+                "remotes": ["https://rubygems.org/"],
+                "revision": null,
+                "revision_date": null,
+                "branch": null,
+                "tag": null,
+            },
+            "date": "2023-01-05T12:00:00Z",
+            "commit": commit,
+            "author": "Alice",
+            "author_email": "alice@example.com",
+            "committer": "Alice",
+            "committer_email": "alice@example.com",
+            "line": line,
+        })
+    };
+    let runtime = |version: &str, line: usize| {
+        json!({
+            "version": version,
+            "date": "2023-01-05T12:00:00Z",
+            "commit": commit,
+            "author": "Alice",
+            "author_email": "alice@example.com",
+            "line": line,
+        })
+    };
+    assert_eq!(
+        report,
+        json!({
+            "gems": [
+                gem("nokogiri", "1.15.4", Some("x86_64-linux"), 4),
+                gem("rack", "2.2.8", None, 5),
+            ],
+            "bundler": runtime("2.4.10", 18),
+            "ruby": runtime("3.2.2p53", 15),
+        })
+    );
+}